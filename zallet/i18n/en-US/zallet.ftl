@@ -50,6 +50,10 @@ migrate-warn-cli-only =
     {-zcashd} supported configuring '{$option}' via both a CLI flag and a config
     file entry. {-zallet} does not support it as a config file entry; you will
     instead need to start {-zallet} with the CLI flag '{$flag}'.
+migrate-warn-command-only =
+    {-zcashd} supported configuring '{$option}' via both a CLI flag and a config
+    file entry. {-zallet} instead provides it as a separate command; you will need
+    to run '{$command}' while {-zallet} is stopped.
 migrate-warn-unsupported =
     {-zallet} does not support an equivalent of the {-zcashd} option '{$option}',
     so its configured value '{$value}' is not being migrated. If this option is
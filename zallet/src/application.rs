@@ -5,12 +5,18 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use abscissa_core::{
     application::{self, AppCell},
     config::{self, CfgCell},
-    trace, Application, FrameworkError, StandardPaths,
+    terminal::component::Terminal,
+    trace, Application, Component, Configurable, FrameworkError, StandardPaths,
 };
 use abscissa_tokio::TokioComponent;
 use i18n_embed::unic_langid::LanguageIdentifier;
+use tracing_subscriber::EnvFilter;
 
-use crate::{cli::EntryPoint, config::ZalletConfig, i18n};
+use crate::{
+    cli::EntryPoint,
+    config::{LogFormat, ZalletConfig},
+    i18n,
+};
 
 /// Application state
 pub static APP: AppCell<ZalletApp> = AppCell::new();
@@ -52,7 +58,17 @@ impl Application for ZalletApp {
     }
 
     fn register_components(&mut self, command: &Self::Cmd) -> Result<(), FrameworkError> {
-        let mut components = self.framework_components(command)?;
+        // Abscissa's tracing component only supports its own format, so for the other
+        // formats we install our own subscriber in its place.
+        let mut components = match self.log_format(command) {
+            LogFormat::Full => self.framework_components(command)?,
+            format => {
+                init_tracing(format, command.verbose);
+                let terminal: Box<dyn Component<Self>> =
+                    Box::new(Terminal::new(self.term_colors(command)));
+                vec![terminal]
+            }
+        };
         components.push(Box::new(TokioComponent::from(
             tokio::runtime::Builder::new_multi_thread()
                 .enable_all()
@@ -84,6 +100,40 @@ impl Application for ZalletApp {
     }
 }
 
+impl ZalletApp {
+    /// Returns the log format selected by the `--log-format` flag, or by the config file
+    /// if the flag is not given.
+    ///
+    /// Logging is configured before the config file is loaded, so this reads it early.
+    /// Any error in the config file is ignored here, and reported when it is loaded
+    /// properly.
+    fn log_format(&mut self, command: &EntryPoint) -> LogFormat {
+        command.log_format.unwrap_or_else(|| {
+            command
+                .config_path()
+                .and_then(|path| self.load_config(&path).ok())
+                .map(|config| config.logging.format())
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Installs a global tracing subscriber that logs in the given format.
+///
+/// This uses the same filter as Abscissa's tracing component: `RUST_LOG` if set,
+/// otherwise `debug` in verbose mode and `info` if not.
+fn init_tracing(format: LogFormat, verbose: bool) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(if verbose { "debug" } else { "info" }));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+
+    match format {
+        LogFormat::Full => builder.init(),
+        LogFormat::Compact => builder.compact().init(),
+        LogFormat::Json => builder.json().flatten_event(true).init(),
+    }
+}
+
 /// Boots the Zallet application, parsing subcommand and options from command-line
 /// arguments, and terminating when complete.
 pub fn boot(requested_languages: Vec<LanguageIdentifier>) {
@@ -2,8 +2,10 @@ use std::path::PathBuf;
 
 use abscissa_core::{Command, Runnable};
 use clap::{builder::Styles, Parser};
+use uuid::Uuid;
+use zcash_protocol::consensus::NetworkType;
 
-use crate::{fl, remote::Servers};
+use crate::{config::LogFormat, fl, network, remote::Servers};
 
 #[derive(Debug, Parser, Command)]
 #[command(author, about, version)]
@@ -28,6 +30,10 @@ pub struct EntryPoint {
     /// Use the specified config file
     #[arg(short, long)]
     pub(crate) config: Option<String>,
+
+    /// The format of log output (overrides `logging.format` in the config file)
+    #[arg(long, value_enum)]
+    pub(crate) log_format: Option<LogFormat>,
 }
 
 #[derive(Debug, Parser, Command, Runnable)]
@@ -35,8 +41,21 @@ pub(crate) enum ZalletCmd {
     /// The `start` subcommand
     Start(StartCmd),
 
+    /// Print an example `zallet.toml` config file that documents every setting.
+    ExampleConfig(ExampleConfigCmd),
+
     /// Generate a `zallet.toml` config from an existing `zcashd.conf` file.
     MigrateZcashdConf(MigrateZcashConfCmd),
+
+    /// Print the unified full viewing key for an account in the wallet.
+    ExportViewingKey(ExportViewingKeyCmd),
+
+    /// Rescan the chain from the wallet birthday on the next start, optionally deleting the
+    /// wallet's transactions first.
+    ZapTxes(ZapTxesCmd),
+
+    /// Copy every readable row of a damaged wallet database into a new database file.
+    SalvageWallet(SalvageWalletCmd),
 }
 
 /// `start` subcommand
@@ -46,8 +65,35 @@ pub(crate) struct StartCmd {
     #[arg(long)]
     #[arg(default_value = "ecc", value_parser = Servers::parse)]
     pub(crate) lwd_server: Servers,
+
+    /// The network the wallet is expected to be using ("main", "test", or "regtest").
+    ///
+    /// This never changes the configured network; Zallet will refuse to start if it does
+    /// not match the `network` setting in the config file.
+    #[arg(long, value_parser = network::kind::parse)]
+    pub(crate) network: Option<NetworkType>,
+
+    /// Check the config, wallet database, and lightwalletd connection, then exit without
+    /// starting Zallet.
+    ///
+    /// Zallet applies any pending wallet database migrations when it starts. With this
+    /// flag, the database is not modified; a warning is logged if it has migrations that
+    /// have not been applied.
+    #[arg(long)]
+    pub(crate) check: bool,
+
+    /// Rescan the chain from this block height on startup.
+    ///
+    /// Must not be below the wallet birthday. Balances may be incomplete until the
+    /// rescan finishes.
+    #[arg(long, value_name = "HEIGHT")]
+    pub(crate) rescan_from: Option<u32>,
 }
 
+/// `example-config` subcommand
+#[derive(Debug, Parser, Command)]
+pub(crate) struct ExampleConfigCmd {}
+
 /// `migrate-zcash-conf` subcommand
 #[derive(Debug, Parser, Command)]
 pub(crate) struct MigrateZcashConfCmd {
@@ -80,3 +126,31 @@ pub(crate) struct MigrateZcashConfCmd {
     #[arg(long)]
     pub(crate) this_is_alpha_code_and_you_will_need_to_redo_the_migration_later: bool,
 }
+
+/// `export-viewing-key` subcommand
+#[derive(Debug, Parser, Command)]
+pub(crate) struct ExportViewingKeyCmd {
+    /// The UUID of the account to export the viewing key for.
+    pub(crate) account_uuid: Uuid,
+}
+
+/// `zap-txes` subcommand
+#[derive(Debug, Parser, Command)]
+pub(crate) struct ZapTxesCmd {
+    /// The zap mode, as in `zcashd`'s `-zapwallettxes` option.
+    ///
+    /// - 1: Keep transaction records, memos, and sent output metadata, and rescan the
+    ///   chain to reattach them.
+    /// - 2: Delete every transaction and everything known about it, then rescan. Sent
+    ///   transactions that were never mined are lost.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub(crate) mode: u8,
+}
+
+/// `salvage-wallet` subcommand
+#[derive(Debug, Parser, Command)]
+pub(crate) struct SalvageWalletCmd {
+    /// The path to write the salvaged wallet database to. It must not already exist.
+    #[arg(short, long)]
+    pub(crate) output: PathBuf,
+}
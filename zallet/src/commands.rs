@@ -2,15 +2,20 @@
 
 use std::path::PathBuf;
 
-use abscissa_core::{config::Override, Configurable, FrameworkError, Runnable};
+use abscissa_core::{config::Override, Configurable, FrameworkError, FrameworkErrorKind, Runnable};
 
 use crate::{
     cli::{EntryPoint, ZalletCmd},
     config::ZalletConfig,
+    error::with_sources,
 };
 
+mod example_config;
+mod export_viewing_key;
 mod migrate_zcash_conf;
+mod salvage_wallet;
 mod start;
+mod zap_txes;
 
 /// Zallet Configuration Filename
 pub const CONFIG_FILE: &str = "zallet.toml";
@@ -40,9 +45,24 @@ impl Configurable<ZalletConfig> for EntryPoint {
     }
 
     fn process_config(&self, config: ZalletConfig) -> Result<ZalletConfig, FrameworkError> {
-        match &self.cmd {
-            ZalletCmd::Start(cmd) => cmd.override_config(config),
-            _ => Ok(config),
+        let config = match &self.cmd {
+            ZalletCmd::Start(cmd) => cmd.override_config(config)?,
+            _ => config,
+        };
+
+        // Commands that generate a config file don't use the loaded one, so they should
+        // still work if it is invalid.
+        if !matches!(
+            self.cmd,
+            ZalletCmd::ExampleConfig(_) | ZalletCmd::MigrateZcashdConf(_)
+        ) {
+            config.validate().map_err(|e| -> FrameworkError {
+                FrameworkErrorKind::ConfigError
+                    .context(with_sources(e.cause()))
+                    .into()
+            })?;
         }
+
+        Ok(config)
     }
 }
@@ -0,0 +1,17 @@
+//! `example-config` subcommand
+
+use abscissa_core::{Runnable, Shutdown};
+
+use crate::{cli::ExampleConfigCmd, config::ZalletConfig, prelude::*};
+
+impl Runnable for ExampleConfigCmd {
+    fn run(&self) {
+        match ZalletConfig::generate_example() {
+            Ok(example) => print!("{example}"),
+            Err(e) => {
+                eprintln!("{}", e);
+                APP.shutdown_with_exitcode(Shutdown::Forced, 1);
+            }
+        }
+    }
+}
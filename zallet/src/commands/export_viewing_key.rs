@@ -0,0 +1,67 @@
+//! `export-viewing-key` subcommand
+
+use abscissa_core::{Runnable, Shutdown};
+use zcash_client_backend::data_api::{Account as _, WalletRead};
+use zcash_client_sqlite::AccountUuid;
+
+use crate::{
+    cli::ExportViewingKeyCmd,
+    components::wallet::Wallet,
+    error::{Error, ErrorKind},
+    prelude::*,
+};
+
+impl ExportViewingKeyCmd {
+    async fn start(&self) -> Result<(), Error> {
+        let config = APP.config();
+        let params = config.network();
+
+        // This only reads from the wallet database, so we don't need lightwalletd. The
+        // wallet is locked while it is open, so another Zallet process can't be changing
+        // it while we read it.
+        let wallet = Wallet::open_offline(config.wallet_db_path()?, params).await?;
+
+        let account = wallet
+            .get_account(AccountUuid::from_uuid(self.account_uuid))
+            .map_err(|e| ErrorKind::Generic.context(e))?
+            .ok_or_else(|| {
+                ErrorKind::Generic.context(format!("Unknown account {}", self.account_uuid))
+            })?;
+
+        // Every account in a `zcash_client_sqlite` wallet has a UFVK.
+        let ufvk = account
+            .ufvk()
+            .ok_or_else(|| ErrorKind::Generic.context("Account has no viewing key"))?;
+
+        // Print the UFVK on its own to stdout so it can be piped elsewhere.
+        println!("{}", ufvk.encode(&params));
+        if let Some(derivation) = account.source().key_derivation() {
+            eprintln!(
+                "Seed fingerprint: {}",
+                hex::encode(derivation.seed_fingerprint().to_bytes())
+            );
+            eprintln!(
+                "ZIP 32 account index: {}",
+                u32::from(derivation.account_index())
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Runnable for ExportViewingKeyCmd {
+    fn run(&self) {
+        match abscissa_tokio::run(&APP, self.start()) {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => {
+                eprintln!("{}", e);
+                APP.shutdown_with_exitcode(Shutdown::Forced, 1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                APP.shutdown_with_exitcode(Shutdown::Forced, 1);
+            }
+        }
+    }
+}
@@ -360,7 +360,7 @@ fn build_actions() -> HashMap<&'static str, Action> {
                 Some(fl!(
                     "migrate-warn-cli-only",
                     option = "rescan",
-                    flag = "--rescan",
+                    flag = "--rescan-from",
                 ))
             }),
         )))
@@ -368,9 +368,9 @@ fn build_actions() -> HashMap<&'static str, Action> {
             "salvagewallet",
             Action::warn(|_| {
                 Some(fl!(
-                    "migrate-warn-cli-only",
+                    "migrate-warn-command-only",
                     option = "salvagewallet",
-                    flag = "--salvage-wallet",
+                    command = "zallet salvage-wallet --output=PATH",
                 ))
             }),
         )))
@@ -409,9 +409,9 @@ fn build_actions() -> HashMap<&'static str, Action> {
             "zapwallettxes",
             Action::warn(|_| {
                 Some(fl!(
-                    "migrate-warn-cli-only",
+                    "migrate-warn-command-only",
                     option = "zapwallettxes",
-                    flag = "--zap-txes=MODE",
+                    command = "zallet zap-txes --mode=MODE",
                 ))
             }),
         )));
@@ -526,8 +526,19 @@ fn build_actions() -> HashMap<&'static str, Action> {
             |config| &mut config.rpc.bind,
             |value| value.parse().map_err(|_| ()),
         ))
-        // TODO
-        .chain(Action::ignore("rpccookiefile"))
+        // `zcashd` resolves relative paths against its datadir, which Zallet doesn't have.
+        .chain(Action::map(
+            "rpccookiefile",
+            |config| &mut config.rpc.cookie_path,
+            |value| {
+                let path = PathBuf::from(value);
+                if path.is_absolute() {
+                    Ok(path)
+                } else {
+                    Err(())
+                }
+            },
+        ))
         .chain(Some((
             "rpcport",
             Action::warn(|_| {
@@ -0,0 +1,170 @@
+//! `salvage-wallet` subcommand
+
+use abscissa_core::{Runnable, Shutdown};
+use rusqlite::{Connection, OpenFlags};
+
+use crate::{
+    cli::SalvageWalletCmd,
+    error::{Error, ErrorKind},
+    prelude::*,
+};
+
+impl SalvageWalletCmd {
+    fn start(&self) -> Result<(), Error> {
+        let config = APP.config();
+        let wallet_db_path = config.wallet_db_path()?;
+
+        if self.output.exists() {
+            return Err(ErrorKind::Generic
+                .context(format!("{} already exists", self.output.display()))
+                .into());
+        }
+
+        let sqlite_error = |e: rusqlite::Error| ErrorKind::Generic.context(e);
+
+        // Report the damage before trying to salvage anything.
+        let source = Connection::open_with_flags(
+            wallet_db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| ErrorKind::Generic.context(format!("{}: {e}", wallet_db_path.display())))?;
+        let problems = source
+            .prepare("PRAGMA integrity_check")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(sqlite_error)?;
+        if problems == ["ok"] {
+            eprintln!("Integrity check found no problems; salvaging anyway");
+        } else {
+            eprintln!("Integrity check found {} problems:", problems.len());
+            for problem in &problems {
+                eprintln!("  {problem}");
+            }
+        }
+        let user_version = source
+            .query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+            .map_err(sqlite_error)?;
+        drop(source);
+
+        let output = Connection::open(&self.output)
+            .map_err(|e| ErrorKind::Generic.context(format!("{}: {e}", self.output.display())))?;
+        output
+            .execute(
+                "ATTACH DATABASE ?1 AS damaged",
+                [wallet_db_path.to_string_lossy()],
+            )
+            .map_err(sqlite_error)?;
+
+        let schema = output
+            .prepare(
+                "SELECT type, name, sql FROM damaged.sqlite_master
+                WHERE sql IS NOT NULL
+                AND name NOT LIKE 'sqlite_%'
+                ORDER BY type != 'table'",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|e| {
+                ErrorKind::Generic.context(format!("Cannot read the wallet database schema: {e}"))
+            })?;
+
+        let mut lost_tables = 0;
+        for (kind, name, sql) in &schema {
+            if let Err(e) = output.execute_batch(sql) {
+                eprintln!("Could not recreate {kind} {name}: {e}");
+                if kind == "table" {
+                    lost_tables += 1;
+                }
+                continue;
+            }
+            if kind == "table" {
+                let (copied, lost) = copy_rows(&output, name);
+                if lost == 0 {
+                    eprintln!("Salvaged table {name}: {copied} rows");
+                } else {
+                    eprintln!("Salvaged table {name}: {copied} rows ({lost} unreadable)");
+                }
+            }
+        }
+
+        output
+            .execute_batch(&format!("PRAGMA user_version = {user_version}"))
+            .map_err(sqlite_error)?;
+        output
+            .execute_batch("DETACH DATABASE damaged")
+            .map_err(sqlite_error)?;
+
+        eprintln!(
+            "Wrote salvaged wallet database to {}",
+            self.output.display()
+        );
+        if lost_tables > 0 {
+            eprintln!("{lost_tables} tables could not be salvaged");
+        }
+        eprintln!(
+            "Set wallet_db to the salvaged file and start Zallet with --rescan-from to \
+            rebuild anything that was lost"
+        );
+
+        Ok(())
+    }
+}
+
+/// Copies the rows of `table` from the damaged database into the salvaged database.
+///
+/// Returns the number of rows copied, and the number that could not be read.
+fn copy_rows(output: &Connection, table: &str) -> (usize, usize) {
+    let table = table.replace('"', "\"\"");
+
+    // Copy the whole table at once if we can.
+    if let Ok(copied) = output.execute(
+        &format!("INSERT INTO main.\"{table}\" SELECT * FROM damaged.\"{table}\""),
+        [],
+    ) {
+        return (copied, 0);
+    }
+
+    // Otherwise, copy whichever rows can be read one at a time. Tables without a rowid
+    // can't be salvaged this way.
+    let rowids = output
+        .prepare(&format!("SELECT rowid FROM damaged.\"{table}\""))
+        .and_then(|mut stmt| {
+            let rows = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+            // Stop at the first unreadable page, keeping the rowids found before it.
+            Ok(rows.map_while(Result::ok).collect::<Vec<_>>())
+        })
+        .unwrap_or_default();
+
+    let (mut copied, mut lost) = (0, 0);
+    for rowid in rowids {
+        match output.execute(
+            &format!(
+                "INSERT INTO main.\"{table}\" SELECT * FROM damaged.\"{table}\" WHERE rowid = ?1"
+            ),
+            [rowid],
+        ) {
+            Ok(n) => copied += n,
+            Err(_) => lost += 1,
+        }
+    }
+    (copied, lost)
+}
+
+impl Runnable for SalvageWalletCmd {
+    fn run(&self) {
+        if let Err(e) = self.start() {
+            eprintln!("{}", e);
+            APP.shutdown_with_exitcode(Shutdown::Forced, 1);
+        }
+    }
+}
@@ -1,14 +1,26 @@
 //! `start` subcommand
 
-use abscissa_core::{config, tracing::Instrument, FrameworkError, Runnable, Shutdown};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use abscissa_core::{
+    config, tracing::Instrument, FrameworkError, FrameworkErrorKind, Runnable, Shutdown,
+};
 use tokio::{pin, select};
+use zcash_client_backend::{data_api::WalletRead, proto::service::Empty};
+use zcash_protocol::consensus::BlockHeight;
 
 use crate::{
     cli::StartCmd,
-    components::{json_rpc, wallet::Wallet},
+    components::{
+        json_rpc,
+        wallet::{apply_migrations, pending_migrations, queue_rescan, Wallet},
+    },
     config::ZalletConfig,
-    error::{Error, ErrorKind},
+    error::{with_sources, Error, ErrorKind},
+    network,
     prelude::*,
+    remote::Servers,
 };
 
 impl StartCmd {
@@ -16,22 +28,116 @@ impl StartCmd {
         let config = APP.config();
 
         // Open the wallet.
-        let wallet = {
-            let path = config
-                .wallet_db
-                .as_ref()
-                .ok_or_else(|| ErrorKind::Init.context("wallet_db must be set (for now)"))?;
-            if path.is_relative() {
-                return Err(ErrorKind::Init
-                    .context("wallet_db must be an absolute path (for now)")
-                    .into());
+        let wallet_db_path = config.wallet_db_path()?;
+        let database_error = |e: &(dyn std::error::Error + 'static)| {
+            ErrorKind::Init.context(format!(
+                "database: {}: {}",
+                wallet_db_path.display(),
+                with_sources(e),
+            ))
+        };
+        let wallet = Wallet::open(wallet_db_path, config.network(), self.lwd_server.clone())
+            .map_err(|e| database_error(e.cause()))?;
+
+        // Bring the wallet database up to date, unless we are only checking it.
+        if self.check {
+            let pending = pending_migrations(wallet_db_path, config.network())?;
+            if pending > 0 {
+                warn!(
+                    "{pending} wallet database migrations have not been applied; `zallet start` applies them when it opens the wallet"
+                );
             }
+        } else {
+            let applied = apply_migrations(wallet_db_path, config.network())?;
+            if applied > 0 {
+                info!("Applied {applied} wallet database migrations");
+            }
+        }
 
-            Wallet::open(path, config.network(), self.lwd_server.clone())?
+        // Check that we can read the wallet database.
+        let (schema_version, account_count, wallet_tip) = {
+            let db_data = wallet
+                .handle()
+                .await
+                .map_err(|e| database_error(e.cause()))?;
+            let account_count = db_data
+                .get_account_ids()
+                .map_err(|e| database_error(&e))?
+                .len();
+            let wallet_tip = db_data.chain_height().map_err(|e| database_error(&e))?;
+            let schema_version = db_data.schema_version().map_err(|e| database_error(&e))?;
+            (schema_version, account_count, wallet_tip)
         };
 
+        let mut summary = StartupSummary::new(
+            &config,
+            &self.lwd_server,
+            schema_version,
+            account_count,
+            wallet_tip,
+        )?;
+
+        if self.check {
+            let lightd_info = wallet
+                .connect_lightwalletd()
+                .await
+                .map_err(|e| ErrorKind::Init.context(with_sources(e.cause())))?
+                .get_lightd_info(Empty {})
+                .await
+                .map_err(|e| {
+                    ErrorKind::Init.context(format!(
+                        "lightwalletd {:?}: {}",
+                        self.lwd_server,
+                        e.message(),
+                    ))
+                })?
+                .into_inner();
+
+            summary.chain_tip = Some(lightd_info.block_height);
+            summary.log("Zallet check passed");
+            return Ok(());
+        }
+
+        if let Some(rescan_from) = self.rescan_from {
+            let mut db_data = wallet
+                .handle()
+                .await
+                .map_err(|e| database_error(e.cause()))?;
+            let birthday = db_data
+                .get_wallet_birthday()
+                .map_err(|e| database_error(&e))?;
+            match birthday {
+                None => warn!("Ignoring --rescan-from because the wallet has no accounts"),
+                Some(birthday) if rescan_from < u32::from(birthday) => {
+                    return Err(ErrorKind::Init
+                        .context(format!(
+                            "--rescan-from must not be below the wallet birthday ({birthday})"
+                        ))
+                        .into())
+                }
+                Some(_) => {
+                    let rescan_from = BlockHeight::from_u32(rescan_from);
+                    let queued = db_data
+                        .as_mut()
+                        .with_raw_mut(|conn| {
+                            let tx = conn.transaction()?;
+                            let queued = queue_rescan(&tx, rescan_from)?;
+                            tx.commit()?;
+                            Ok::<_, rusqlite::Error>(queued)
+                        })
+                        .map_err(|e| database_error(&e))?;
+                    match queued {
+                        Some(range) => info!("Rescanning the chain from height {}", range.start),
+                        None => warn!(
+                            "Ignoring --rescan-from because the wallet has not seen height {rescan_from} yet"
+                        ),
+                    }
+                }
+            }
+        }
+
         // Launch RPC server.
-        let rpc_task_handle = if !config.rpc.bind.is_empty() {
+        let (rpc_task_handle, rpc_addr) = if !config.rpc.bind.is_empty() {
             if config.rpc.bind.len() > 1 {
                 return Err(ErrorKind::Init
                     .context("Only one RPC bind address is supported (for now)")
@@ -39,21 +145,42 @@ impl StartCmd {
             }
             info!("Spawning RPC server");
             info!("Trying to open RPC endpoint at {}...", config.rpc.bind[0]);
-            json_rpc::server::spawn(config.rpc.clone(), wallet.clone()).await?
+            let (task, addr) = json_rpc::server::spawn(&config, wallet.clone()).await?;
+            (task, Some(addr))
         } else {
             warn!("Configure `rpc.bind` to start the RPC server");
             // Emulate a normally-operating ongoing task to simplify subsequent logic.
-            tokio::spawn(std::future::pending().in_current_span())
+            (tokio::spawn(std::future::pending().in_current_span()), None)
         };
 
         // Start the wallet sync process.
-        let wallet_sync_task_handle = wallet.spawn_sync().await?;
+        let wallet_sync_task_handle = wallet
+            .spawn_sync(
+                config.indexer.batch_size(),
+                config.indexer.recovery_idle_interval(),
+                config.indexer.connect_retries(),
+                config.indexer.upgrade_warning_blocks(),
+                config.notify.clone(),
+            )
+            .await
+            .map_err(|e| ErrorKind::Init.context(format!("sync: {}", with_sources(e.cause()))))?;
+
+        // Start the balance consistency check, if enabled.
+        let balance_check_task_handle = match config.features.balance_check_interval() {
+            Some(interval) => wallet.spawn_balance_check(interval).await?,
+            // Emulate a normally-operating ongoing task to simplify subsequent logic.
+            None => tokio::spawn(std::future::pending().in_current_span()),
+        };
 
         info!("Spawned Zallet tasks");
 
+        summary.rpc_addr = rpc_addr;
+        summary.log("Zallet started");
+
         // ongoing tasks.
         pin!(rpc_task_handle);
         pin!(wallet_sync_task_handle);
+        pin!(balance_check_task_handle);
 
         // Wait for tasks to finish.
         let res = loop {
@@ -73,6 +200,13 @@ impl StartCmd {
                     info!(?wallet_sync_result, "Wallet sync task exited");
                     Ok(())
                 }
+
+                balance_check_join_result = &mut balance_check_task_handle => {
+                    let balance_check_result = balance_check_join_result
+                        .expect("unexpected panic in the balance check task");
+                    info!(?balance_check_result, "Balance check task exited");
+                    Ok(())
+                }
             };
 
             // Stop Zallet if a task finished and returned an error, or if an ongoing task
@@ -89,6 +223,7 @@ impl StartCmd {
         // ongoing tasks
         rpc_task_handle.abort();
         wallet_sync_task_handle.abort();
+        balance_check_task_handle.abort();
 
         info!("All tasks have been asked to stop, waiting for remaining tasks to finish");
 
@@ -96,6 +231,66 @@ impl StartCmd {
     }
 }
 
+/// The facts that deployment tooling checks to confirm that Zallet started with the
+/// intended configuration.
+#[derive(Debug)]
+struct StartupSummary {
+    version: &'static str,
+    network: &'static str,
+    wallet_db: PathBuf,
+    /// The number of database migrations that have been applied to the wallet.
+    schema_version: u32,
+    accounts: usize,
+    /// The address that the RPC server is bound to, if it was started.
+    rpc_addr: Option<SocketAddr>,
+    /// The `lightwalletd` server that the wallet syncs with, if one serves the network.
+    lwd_server: Option<String>,
+    wallet_tip: Option<u32>,
+    /// The chain tip reported by `lightwalletd`, if Zallet has asked for it.
+    chain_tip: Option<u64>,
+}
+
+impl StartupSummary {
+    fn new(
+        config: &ZalletConfig,
+        lwd_servers: &Servers,
+        schema_version: u32,
+        accounts: usize,
+        wallet_tip: Option<BlockHeight>,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION"),
+            network: network::kind::name(&config.network),
+            wallet_db: config.wallet_db_path()?.to_path_buf(),
+            schema_version,
+            accounts,
+            rpc_addr: None,
+            lwd_server: lwd_servers
+                .pick(config.network())
+                .ok()
+                .map(|server| server.to_string()),
+            wallet_tip: wallet_tip.map(u32::from),
+            chain_tip: None,
+        })
+    }
+
+    /// Logs the summary as a single line, which deployment tooling can check for.
+    fn log(&self, message: &str) {
+        info!(
+            version = self.version,
+            network = self.network,
+            wallet_db = %self.wallet_db.display(),
+            schema_version = self.schema_version,
+            accounts = self.accounts,
+            rpc_addr = ?self.rpc_addr,
+            lwd_server = ?self.lwd_server,
+            wallet_tip = ?self.wallet_tip,
+            chain_tip = ?self.chain_tip,
+            "{message}",
+        );
+    }
+}
+
 impl Runnable for StartCmd {
     fn run(&self) {
         match abscissa_tokio::run(&APP, self.start()) {
@@ -114,6 +309,81 @@ impl Runnable for StartCmd {
 
 impl config::Override<ZalletConfig> for StartCmd {
     fn override_config(&self, config: ZalletConfig) -> Result<ZalletConfig, FrameworkError> {
+        // The network cannot change for the lifetime of the wallet, so `--network` only
+        // asserts the operator's expectation rather than overriding the config.
+        if let Some(expected) = self.network {
+            if expected != config.network {
+                return Err(FrameworkErrorKind::ConfigError
+                    .context(format!(
+                        "--network {} was given, but the config file is for network '{}'",
+                        network::kind::name(&expected),
+                        network::kind::name(&config.network),
+                    ))
+                    .into());
+            }
+        }
+
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use zcash_protocol::consensus::{BlockHeight, NetworkType};
+
+    use super::StartupSummary;
+    use crate::{config::ZalletConfig, remote::Servers};
+
+    #[test]
+    fn startup_summary_reflects_config() {
+        let config = ZalletConfig {
+            network: NetworkType::Test,
+            wallet_db: Some("/srv/zallet/wallet.db".into()),
+            ..Default::default()
+        };
+        let servers = Servers::parse("lwd.example.com:9067").unwrap();
+
+        let summary =
+            StartupSummary::new(&config, &servers, 42, 2, Some(BlockHeight::from_u32(1000)))
+                .unwrap();
+
+        assert_eq!(summary.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(summary.network, "test");
+        assert_eq!(summary.wallet_db, Path::new("/srv/zallet/wallet.db"));
+        assert_eq!(summary.schema_version, 42);
+        assert_eq!(summary.accounts, 2);
+        assert_eq!(summary.lwd_server.as_deref(), Some("lwd.example.com:9067"));
+        assert_eq!(summary.wallet_tip, Some(1000));
+        assert_eq!(summary.rpc_addr, None);
+        assert_eq!(summary.chain_tip, None);
+    }
+
+    #[test]
+    fn startup_summary_uses_hosted_server_for_network() {
+        let servers = Servers::parse("ecc").unwrap();
+        let summary_for = |network| {
+            let config = ZalletConfig {
+                network,
+                wallet_db: Some("/srv/zallet/wallet.db".into()),
+                ..Default::default()
+            };
+            StartupSummary::new(&config, &servers, 42, 0, None).unwrap()
+        };
+
+        let main = summary_for(NetworkType::Main);
+        let test = summary_for(NetworkType::Test);
+        assert_eq!(main.network, "main");
+        assert_eq!(test.network, "test");
+        assert!(main.lwd_server.is_some());
+        assert_ne!(main.lwd_server, test.lwd_server);
+        assert_eq!(main.wallet_tip, None);
+    }
+
+    #[test]
+    fn startup_summary_requires_wallet_db() {
+        let servers = Servers::parse("ecc").unwrap();
+        assert!(StartupSummary::new(&ZalletConfig::default(), &servers, 42, 0, None).is_err());
+    }
+}
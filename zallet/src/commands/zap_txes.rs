@@ -0,0 +1,228 @@
+//! `zap-txes` subcommand
+
+use std::ops::Range;
+
+use abscissa_core::{Runnable, Shutdown};
+use zcash_client_backend::data_api::WalletRead;
+use zcash_protocol::consensus::BlockHeight;
+
+use crate::{
+    cli::ZapTxesCmd,
+    components::wallet::{queue_rescan, Wallet},
+    error::{Error, ErrorKind},
+    prelude::*,
+};
+
+/// The tables that hold data about wallet transactions, other than `transactions` itself.
+///
+/// Tables that refer to other tables in this list come first, so that the rows can be
+/// deleted in order.
+const TRANSACTION_TABLES: &[&str] = &[
+    "sapling_received_note_spends",
+    "orchard_received_note_spends",
+    "transparent_received_output_spends",
+    "sapling_received_notes",
+    "orchard_received_notes",
+    "transparent_received_outputs",
+    "sent_notes",
+    "transparent_spend_map",
+    "transparent_spend_search_queue",
+    "tx_retrieval_queue",
+];
+
+impl ZapTxesCmd {
+    async fn start(&self) -> Result<(), Error> {
+        let config = APP.config();
+
+        // This locks the wallet, so it fails if a running Zallet is using it.
+        let mut wallet = Wallet::open_offline(config.wallet_db_path()?, config.network()).await?;
+
+        let Some(birthday) = wallet
+            .get_wallet_birthday()
+            .map_err(|e| ErrorKind::Generic.context(e))?
+        else {
+            eprintln!("The wallet has no accounts; nothing to zap");
+            return Ok(());
+        };
+
+        let mode = self.mode;
+        let (deleted, rescan) = wallet
+            .with_raw_mut(|conn| zap(conn, mode, birthday))
+            .map_err(|e| ErrorKind::Generic.context(e))?;
+
+        if let Some(deleted) = deleted {
+            eprintln!("Deleted {deleted} transactions");
+        }
+        match rescan {
+            Some(range) => eprintln!(
+                "Blocks {} to {} will be rescanned when Zallet next starts",
+                range.start,
+                range.end - 1,
+            ),
+            None => eprintln!(
+                "The wallet has not scanned any blocks; they will be scanned when Zallet next starts"
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+/// Zaps the wallet in the given mode, and queues the chain from the wallet `birthday` to
+/// be rescanned.
+///
+/// Returns the number of transactions deleted (in mode 2), and the range of blocks that
+/// will be rescanned. Either everything is changed, or nothing is.
+fn zap(
+    conn: &mut rusqlite::Connection,
+    mode: u8,
+    birthday: BlockHeight,
+) -> rusqlite::Result<(Option<usize>, Option<Range<BlockHeight>>)> {
+    let tx = conn.transaction()?;
+    // In mode 1, transaction records, memos, and sent output metadata are kept, and the
+    // rescan reattaches them to the chain.
+    let deleted = (mode == 2).then(|| drop_transactions(&tx)).transpose()?;
+    let rescan = queue_rescan(&tx, birthday)?;
+    tx.commit()?;
+    Ok((deleted, rescan))
+}
+
+/// Deletes every wallet transaction, along with the notes, outputs, memos, and other
+/// records that refer to them, and returns the number of transactions deleted.
+///
+/// Transactions that the wallet created but were never mined can't be recovered by a
+/// rescan, so they are lost.
+fn drop_transactions(tx: &rusqlite::Transaction<'_>) -> rusqlite::Result<usize> {
+    for table in TRANSACTION_TABLES {
+        tx.execute(&format!("DELETE FROM {table}"), [])?;
+    }
+
+    // Ephemeral addresses stay reserved, so that they are not handed out again; we only
+    // forget the transactions that used them.
+    tx.execute(
+        "UPDATE ephemeral_addresses SET used_in_tx = NULL, seen_in_tx = NULL",
+        [],
+    )?;
+
+    tx.execute("DELETE FROM transactions", [])
+}
+
+impl Runnable for ZapTxesCmd {
+    fn run(&self) {
+        match abscissa_tokio::run(&APP, self.start()) {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => {
+                eprintln!("{}", e);
+                APP.shutdown_with_exitcode(Shutdown::Forced, 1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                APP.shutdown_with_exitcode(Shutdown::Forced, 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zcash_client_backend::data_api::{scanning::ScanPriority, WalletRead};
+    use zcash_client_sqlite::WalletDb;
+    use zcash_protocol::consensus::BlockHeight;
+
+    use super::{zap, TRANSACTION_TABLES};
+    use crate::components::wallet::testing;
+
+    const TIP: u32 = 2_000_000;
+
+    fn count(conn: &rusqlite::Connection, query: &str) -> u32 {
+        conn.query_row(query, [], |row| row.get(0)).unwrap()
+    }
+
+    /// Checks that every block from the wallet birthday to the tip will be rescanned.
+    fn assert_rescan_queued(path: &std::path::Path, birthday: BlockHeight) {
+        let db = WalletDb::for_path(path, testing::network()).unwrap();
+        let ranges = db.suggest_scan_ranges().unwrap();
+        assert!(ranges
+            .iter()
+            .all(|range| range.priority() >= ScanPriority::Historic));
+        assert_eq!(
+            ranges.iter().map(|range| range.block_range().start).min(),
+            Some(birthday),
+        );
+        assert_eq!(
+            ranges
+                .iter()
+                .map(|range| u32::from(range.block_range().end)
+                    - u32::from(range.block_range().start))
+                .sum::<u32>(),
+            TIP + 1 - u32::from(birthday),
+        );
+    }
+
+    /// Adds a transaction that used one of the account's ephemeral addresses.
+    fn add_transaction(conn: &rusqlite::Connection) {
+        conn.execute(
+            "INSERT INTO transactions (txid, mined_height) VALUES (?1, ?2)",
+            (vec![1u8; 32], TIP - 10),
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE ephemeral_addresses
+            SET used_in_tx = last_insert_rowid(), seen_in_tx = last_insert_rowid()
+            WHERE address_index = 0",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn zap_mode_1_keeps_transactions() {
+        let (path, birthday) = testing::synced_wallet_db("zap-mode-1", TIP);
+        let mut conn = rusqlite::Connection::open(&path).unwrap();
+        add_transaction(&conn);
+
+        let (deleted, rescan) = zap(&mut conn, 1, birthday).unwrap();
+        assert_eq!(deleted, None);
+        assert_eq!(rescan.map(|range| range.start), Some(birthday));
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM transactions"), 1);
+        assert_rescan_queued(&path, birthday);
+
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn zap_mode_2_deletes_transactions() {
+        let (path, birthday) = testing::synced_wallet_db("zap-mode-2", TIP);
+        let mut conn = rusqlite::Connection::open(&path).unwrap();
+        add_transaction(&conn);
+        let ephemeral_addresses = count(&conn, "SELECT COUNT(*) FROM ephemeral_addresses");
+        assert!(ephemeral_addresses > 0);
+
+        let (deleted, rescan) = zap(&mut conn, 2, birthday).unwrap();
+        assert_eq!(deleted, Some(1));
+        assert_eq!(rescan.map(|range| range.start), Some(birthday));
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM transactions"), 0);
+        for table in TRANSACTION_TABLES {
+            assert_eq!(count(&conn, &format!("SELECT COUNT(*) FROM {table}")), 0);
+        }
+
+        // The ephemeral addresses are still reserved, but no longer used.
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM ephemeral_addresses"),
+            ephemeral_addresses,
+        );
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM ephemeral_addresses
+                WHERE used_in_tx IS NOT NULL OR seen_in_tx IS NOT NULL"
+            ),
+            0,
+        );
+        assert_rescan_queued(&path, birthday);
+
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -6,12 +6,41 @@
 //! - Some methods have the same name but slightly different semantics.
 //! - Some methods from the `zcashd` wallet are unsupported.
 
-use zcash_protocol::value::{Zatoshis, COIN};
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned as RpcError};
+use zcash_protocol::{
+    value::{ZatBalance, Zatoshis, COIN},
+    TxId,
+};
+
+use self::server::LegacyCode;
 
 pub(crate) mod methods;
 pub(crate) mod server;
+mod tx_status;
 
 // TODO: https://github.com/zcash/wallet/issues/15
 fn value_from_zatoshis(value: Zatoshis) -> f64 {
     (u64::from(value) as f64) / (COIN as f64)
 }
+
+// TODO: https://github.com/zcash/wallet/issues/15
+fn value_from_zat_balance(value: ZatBalance) -> f64 {
+    (i64::from(value) as f64) / (COIN as f64)
+}
+
+/// Formats a value in ZEC with 8 decimal places, matching `zcashd`'s `FormatMoney`.
+fn value_to_string(value: Zatoshis) -> String {
+    let zat = u64::from(value);
+    format!("{}.{:08}", zat / COIN, zat % COIN)
+}
+
+/// Parses a transaction ID from its RPC encoding (byte-reversed hex, as displayed by
+/// block explorers and `zcashd`).
+fn parse_txid(txid_str: &str) -> RpcResult<TxId> {
+    let mut bytes = [0; 32];
+    hex::decode_to_slice(txid_str, &mut bytes).map_err(|_| {
+        RpcError::borrowed(LegacyCode::InvalidParameter.into(), "invalid txid", None)
+    })?;
+    bytes.reverse();
+    Ok(TxId::from_bytes(bytes))
+}
@@ -1,18 +1,88 @@
 use async_trait::async_trait;
-use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use jsonrpsee::{core::RpcResult, proc_macros::rpc, types::ErrorObjectOwned as RpcError};
+use tonic::transport::Channel;
+use zcash_client_backend::proto::service::compact_tx_streamer_client::CompactTxStreamerClient;
 
-use crate::components::wallet::{Wallet, WalletHandle};
+use crate::{
+    components::{
+        json_rpc::server::LegacyCode,
+        wallet::{Wallet, WalletHandle},
+    },
+    config::{BuilderSection, FeaturesSection, LimitsSection, ZalletConfig},
+};
 
+mod export_viewing_key;
+mod get_account_activity;
+mod get_address_for_account;
+mod get_best_block_hash;
+mod get_block_count;
+mod get_block_hash;
+mod get_info;
+mod get_note_statistics;
 mod get_notes_count;
+mod get_received_by_address;
+mod get_sync_status;
+mod get_total_balance;
+mod get_transaction;
 mod get_wallet_info;
+mod import_viewing_key;
 mod list_accounts;
+mod list_received_by_address;
+mod list_since_block;
+mod list_transactions;
 mod list_unified_receivers;
 mod list_unspent;
+mod rescan_blockchain;
+mod rescan_transaction;
 
 #[rpc(server)]
 pub(crate) trait Rpc {
     #[method(name = "getwalletinfo")]
-    fn get_wallet_info(&self) -> get_wallet_info::Response;
+    async fn get_wallet_info(&self) -> get_wallet_info::Response;
+
+    /// Returns information about Zallet, the network it is configured for, and its
+    /// connection to `lightwalletd`.
+    ///
+    /// `connected` is `false` if `lightwalletd` cannot be reached (including while Zallet
+    /// is still making its first connection), in which case the chain tip and consensus
+    /// branch ID are omitted.
+    ///
+    /// `upgrades` lists the network upgrades that this version of Zallet knows about,
+    /// with their consensus branch IDs, so that operators can check which hosts need
+    /// upgrading. `upcoming_upgrade` is the next of these after the chain tip, if any.
+    #[method(name = "getinfo")]
+    async fn get_info(&self) -> get_info::Response;
+
+    /// Returns the progress of the wallet's sync with the chain.
+    ///
+    /// `in_initial_recovery` is `true` while the wallet is still scanning chain history
+    /// between its birthday and the chain tip; scripts can wait for it to become `false`
+    /// before relying on wallet balances. It stays `false` while the wallet is only
+    /// scanning new blocks at the chain tip.
+    ///
+    /// `network_tip_height` is the chain tip reported by `lightwalletd`, which can be
+    /// ahead of the wallet's `chain_tip_height` if sync has stalled.
+    #[method(name = "getsyncstatus")]
+    async fn get_sync_status(&self) -> get_sync_status::Response;
+
+    /// Returns the height of the most recent block in the best valid chain, as reported
+    /// by the configured `lightwalletd` server.
+    #[method(name = "getblockcount")]
+    async fn get_block_count(&self) -> get_block_count::Response;
+
+    /// Returns the hash of the most recent block in the best valid chain, as reported by
+    /// the configured `lightwalletd` server.
+    #[method(name = "getbestblockhash")]
+    async fn get_best_block_hash(&self) -> get_best_block_hash::Response;
+
+    /// Returns the hash of the block at the given height in the best valid chain, as
+    /// reported by the configured `lightwalletd` server.
+    ///
+    /// # Arguments
+    /// - `index` (numeric, required) The block height. Negative values count back from
+    ///   the chain tip, so -1 is the chain tip.
+    #[method(name = "getblockhash")]
+    async fn get_block_hash(&self, index: i64) -> get_block_hash::Response;
 
     #[method(name = "z_listaccounts")]
     async fn list_accounts(&self) -> list_accounts::Response;
@@ -28,9 +98,24 @@ pub(crate) trait Rpc {
     /// returned, even though they are not immediately spendable.
     ///
     /// # Arguments
-    /// - `minconf` (default = 1)
+    /// - `minconf` (numeric, optional, default=1) The minimum confirmations to filter.
+    /// - `maxconf` (numeric, optional, default=9999999) The maximum confirmations to
+    ///   filter.
+    /// - `includeWatchonly` (bool, optional) Whether to include notes in watchonly
+    ///   accounts. If omitted, notes in every account are included.
+    /// - `addresses` (array, optional) Only include notes sent to these Sapling or
+    ///   unified addresses, which must belong to the wallet.
+    /// - `account` (string, optional) Only include notes belonging to the account with
+    ///   this UUID. This is a Zallet extension.
     #[method(name = "z_listunspent")]
-    async fn list_unspent(&self) -> list_unspent::Response;
+    async fn list_unspent(
+        &self,
+        minconf: Option<u32>,
+        maxconf: Option<u32>,
+        include_watchonly: Option<bool>,
+        addresses: Option<Vec<String>>,
+        account: Option<&str>,
+    ) -> list_unspent::Response;
 
     #[method(name = "z_getnotescount")]
     async fn get_notes_count(
@@ -38,16 +123,226 @@ pub(crate) trait Rpc {
         minconf: Option<u32>,
         as_of_height: Option<i32>,
     ) -> get_notes_count::Response;
+
+    /// Returns statistics about the fragmentation of each account's unspent shielded
+    /// notes, to help decide whether (and at what cost) to consolidate them.
+    ///
+    /// For each account and pool, this reports the note count, total value, largest
+    /// note, a histogram of note values, the minimum number of notes needed to cover
+    /// common payment sizes, and an estimate of the ZIP 317 fee to merge all non-dust
+    /// notes (respecting `limits.orchard_actions`).
+    #[method(name = "z_getnotestatistics")]
+    async fn get_note_statistics(&self) -> get_note_statistics::Response;
+
+    /// Returns detailed information about an in-wallet transaction.
+    ///
+    /// # Arguments
+    /// - `txid` (string, required) The transaction ID.
+    #[method(name = "gettransaction")]
+    async fn get_transaction(&self, txid: &str) -> get_transaction::Response;
+
+    /// Returns the total value of funds stored in the node's wallet.
+    ///
+    /// # Arguments
+    /// - `minconf` (numeric, optional, default=1) Only include notes and UTXOs with at
+    ///   least this many confirmations.
+    /// - `includeWatchonly` (bool, optional) Whether to include balances in view-only
+    ///   accounts. If omitted, balances in every account are included.
+    /// - `asOfHeight` (numeric, optional) Execute the query as if it were run when the
+    ///   blockchain was at this height. Must not be greater than the chain tip; -1 means
+    ///   the chain tip.
+    #[method(name = "z_gettotalbalance")]
+    async fn get_total_balance(
+        &self,
+        minconf: Option<u32>,
+        include_watchonly: Option<bool>,
+        as_of_height: Option<i32>,
+    ) -> get_total_balance::Response;
+
+    /// Returns the wallet transactions whose status has changed since the given block.
+    ///
+    /// Integrators can poll this incrementally by passing the returned `lastblock` as the
+    /// `blockhash` argument of the next call. If `blockhash` is not in the main chain
+    /// (for example, because it was removed by a reorg), the error code -5 "Block not
+    /// found" is returned, and the caller should start again from an earlier block.
+    ///
+    /// # Arguments
+    /// - `blockhash` (string, optional) The block hash to list transactions since.
+    /// - `target_confirmations` (numeric, optional, default=1) Return the hash of the
+    ///   block with this many confirmations as `lastblock`.
+    #[method(name = "listsinceblock")]
+    async fn list_since_block(
+        &self,
+        blockhash: Option<&str>,
+        target_confirmations: Option<u32>,
+    ) -> list_since_block::Response;
+
+    /// Fetches a transaction from the chain and re-decrypts it with the wallet's current
+    /// keys, storing any newly-discovered effects on the wallet.
+    ///
+    /// This is much cheaper than a full rescan when a viewing key has been imported after
+    /// a transaction of interest was scanned.
+    ///
+    /// # Arguments
+    /// - `txid` (string, required) The transaction ID.
+    #[method(name = "rescantransaction")]
+    async fn rescan_transaction(&self, txid: &str) -> rescan_transaction::Response;
+
+    /// Rolls the wallet back so that it rescans the chain from the given height.
+    ///
+    /// The rescan happens in the background; use `getsyncstatus` to follow its progress.
+    /// Balances may be incomplete until it finishes.
+    ///
+    /// # Arguments
+    /// - `start_height` (numeric, optional) The block height to rescan from. Must not be
+    ///   below the wallet birthday, which is the default.
+    #[method(name = "rescanblockchain")]
+    async fn rescan_blockchain(&self, start_height: Option<u32>) -> rescan_blockchain::Response;
+
+    /// Returns up to `count` of the most recent wallet transactions, skipping the first
+    /// `from` transactions.
+    ///
+    /// Each entry describes the effect of a transaction on a single account.
+    ///
+    /// # Arguments
+    /// - `account` (string, optional, default="*") The UUID of the account to list
+    ///   transactions for, or `"*"` for all accounts.
+    /// - `count` (numeric, optional, default=10) The number of transactions to return.
+    /// - `from` (numeric, optional, default=0) The number of transactions to skip.
+    #[method(name = "listtransactions")]
+    async fn list_transactions(
+        &self,
+        account: Option<&str>,
+        count: Option<u32>,
+        from: Option<u32>,
+    ) -> list_transactions::Response;
+
+    /// Returns the unified full viewing key for the given account, along with its ZIP 32
+    /// derivation information (seed fingerprint and account index) if known.
+    ///
+    /// # Arguments
+    /// - `account` (string, required) The account UUID.
+    #[method(name = "z_exportviewingkey")]
+    async fn export_viewing_key(&self, account: &str) -> export_viewing_key::Response;
+
+    /// Imports a unified full viewing key as a new view-only account.
+    ///
+    /// The wallet will scan the chain for the account's transactions from `startHeight`.
+    ///
+    /// A Sapling extended full viewing key can be imported in place of a unified full
+    /// viewing key; it is wrapped in a unified full viewing key with only a Sapling
+    /// component. An Orchard-only account can be imported as a unified full viewing key
+    /// with only an Orchard component, as there is no standalone Orchard encoding.
+    ///
+    /// # Arguments
+    /// - `vkey` (string, required) The unified full viewing key, or Sapling extended
+    ///   full viewing key, for this network.
+    /// - `startHeight` (numeric, optional, default=Sapling activation height) The block
+    ///   height at which the key was created.
+    /// - `skip_rescan` (boolean, optional, default=false) Don't start scanning for the
+    ///   account's past transactions now. The account still has its birthday at
+    ///   `startHeight`, so they are found when the sync task next runs.
+    ///
+    /// The response includes the range of blocks queued for scanning (`rescan`), unless
+    /// `skip_rescan` is set.
+    #[method(name = "z_importviewingkey")]
+    async fn import_viewing_key(
+        &self,
+        vkey: &str,
+        start_height: Option<u32>,
+        skip_rescan: Option<bool>,
+    ) -> import_viewing_key::Response;
+
+    /// Returns the total amount received by the given transparent address in
+    /// transactions with at least `minconf` confirmations.
+    ///
+    /// The address must belong to the wallet.
+    ///
+    /// # Arguments
+    /// - `address` (string, required) The transparent address.
+    /// - `minconf` (numeric, optional, default=1) Only include transactions confirmed at
+    ///   least this many times.
+    #[method(name = "getreceivedbyaddress")]
+    async fn get_received_by_address(
+        &self,
+        address: &str,
+        minconf: Option<u32>,
+    ) -> get_received_by_address::Response;
+
+    /// Returns the amounts received by each of the wallet's transparent addresses, along
+    /// with the transactions that paid them.
+    ///
+    /// # Arguments
+    /// - `minconf` (numeric, optional, default=1) Only include transactions confirmed at
+    ///   least this many times.
+    /// - `include_empty` (boolean, optional, default=false) Also list addresses that have
+    ///   not received any payments.
+    /// - `address_filter` (string, optional) Only list this address, which must belong to
+    ///   the wallet.
+    #[method(name = "listreceivedbyaddress")]
+    async fn list_received_by_address(
+        &self,
+        minconf: Option<u32>,
+        include_empty: Option<bool>,
+        address_filter: Option<&str>,
+    ) -> list_received_by_address::Response;
+
+    /// Returns a summary of an account's activity over a range of block heights.
+    ///
+    /// Only mined transactions are included. The range includes `from_height` and
+    /// excludes `to_height`.
+    ///
+    /// # Arguments
+    /// - `account` (string, required) The account UUID.
+    /// - `from_height` (numeric, required) The first block height to include.
+    /// - `to_height` (numeric, optional, default=chain tip + 1) The block height at which
+    ///   to stop.
+    #[method(name = "z_getaccountactivity")]
+    async fn get_account_activity(
+        &self,
+        account: &str,
+        from_height: u32,
+        to_height: Option<u32>,
+    ) -> get_account_activity::Response;
+
+    /// Returns a unified address for the given account.
+    ///
+    /// If no diversifier index is given, the next unused diversifier index is used.
+    /// Requesting a diversifier index that has already been used returns the same address
+    /// again.
+    ///
+    /// # Arguments
+    /// - `account` (string, required) The account UUID.
+    /// - `receiver_types` (array, optional, default=all supported) The receiver types to
+    ///   include in the address, from `["p2pkh", "sapling", "orchard"]`. At least one
+    ///   shielded receiver type is required.
+    /// - `diversifier_index` (numeric, optional) The diversifier index to generate the
+    ///   address at.
+    #[method(name = "z_getaddressforaccount")]
+    async fn get_address_for_account(
+        &self,
+        account: &str,
+        receiver_types: Option<Vec<String>>,
+        diversifier_index: Option<u128>,
+    ) -> get_address_for_account::Response;
 }
 
 pub(crate) struct RpcImpl {
     wallet: Wallet,
+    builder: BuilderSection,
+    features: FeaturesSection,
+    limits: LimitsSection,
 }
 
 impl RpcImpl {
     /// Creates a new instance of the RPC handler.
-    pub(crate) fn new(wallet: Wallet) -> Self {
-        Self { wallet }
+    pub(crate) fn new(config: &ZalletConfig, wallet: Wallet) -> Self {
+        Self {
+            wallet,
+            builder: config.builder.clone(),
+            features: config.features.clone(),
+            limits: config.limits.clone(),
+        }
     }
 
     async fn wallet(&self) -> RpcResult<WalletHandle> {
@@ -56,12 +351,57 @@ impl RpcImpl {
             .await
             .map_err(|_| jsonrpsee::types::ErrorCode::InternalError.into())
     }
+
+    /// Returns the wallet's connection to `lightwalletd`.
+    ///
+    /// Until the sync task has connected, methods that need the chain are in warmup.
+    fn lightwalletd(&self) -> RpcResult<CompactTxStreamerClient<Channel>> {
+        self.wallet.chain_client().ok_or_else(|| {
+            RpcError::borrowed(
+                LegacyCode::InWarmup.into(),
+                "Waiting for the connection to lightwalletd",
+                None,
+            )
+        })
+    }
 }
 
 #[async_trait]
 impl RpcServer for RpcImpl {
-    fn get_wallet_info(&self) -> get_wallet_info::Response {
-        get_wallet_info::call()
+    async fn get_wallet_info(&self) -> get_wallet_info::Response {
+        get_wallet_info::call(
+            self.wallet().await?.as_ref(),
+            self.wallet.chain_client(),
+            self.wallet.notify_status(),
+            &self.builder,
+            &self.features.deprecated,
+        )
+        .await
+    }
+
+    async fn get_info(&self) -> get_info::Response {
+        let lightwalletd = self
+            .wallet
+            .lightwalletd_server()
+            .map_err(|_| jsonrpsee::types::ErrorCode::InternalError)?;
+        let client = self.wallet.chain_client();
+        get_info::call(self.wallet().await?.as_ref(), lightwalletd, client).await
+    }
+
+    async fn get_sync_status(&self) -> get_sync_status::Response {
+        get_sync_status::call(self.wallet().await?.as_ref(), self.wallet.chain_client()).await
+    }
+
+    async fn get_block_count(&self) -> get_block_count::Response {
+        get_block_count::call(self.lightwalletd()?).await
+    }
+
+    async fn get_best_block_hash(&self) -> get_best_block_hash::Response {
+        get_best_block_hash::call(self.lightwalletd()?).await
+    }
+
+    async fn get_block_hash(&self, index: i64) -> get_block_hash::Response {
+        get_block_hash::call(self.lightwalletd()?, index).await
     }
 
     async fn list_accounts(&self) -> list_accounts::Response {
@@ -72,8 +412,22 @@ impl RpcServer for RpcImpl {
         list_unified_receivers::call(unified_address)
     }
 
-    async fn list_unspent(&self) -> list_unspent::Response {
-        list_unspent::call(self.wallet().await?.as_ref())
+    async fn list_unspent(
+        &self,
+        minconf: Option<u32>,
+        maxconf: Option<u32>,
+        include_watchonly: Option<bool>,
+        addresses: Option<Vec<String>>,
+        account: Option<&str>,
+    ) -> list_unspent::Response {
+        list_unspent::call(
+            self.wallet().await?.as_ref(),
+            minconf,
+            maxconf,
+            include_watchonly,
+            addresses,
+            account,
+        )
     }
 
     async fn get_notes_count(
@@ -83,4 +437,139 @@ impl RpcServer for RpcImpl {
     ) -> get_notes_count::Response {
         get_notes_count::call(self.wallet().await?.as_ref(), minconf, as_of_height)
     }
+
+    async fn get_note_statistics(&self) -> get_note_statistics::Response {
+        get_note_statistics::call(self.wallet().await?.as_ref(), &self.builder, &self.limits)
+    }
+
+    async fn get_transaction(&self, txid: &str) -> get_transaction::Response {
+        get_transaction::call(self.wallet().await?.as_ref(), txid)
+    }
+
+    async fn get_total_balance(
+        &self,
+        minconf: Option<u32>,
+        include_watchonly: Option<bool>,
+        as_of_height: Option<i32>,
+    ) -> get_total_balance::Response {
+        get_total_balance::call(
+            self.wallet().await?.as_ref(),
+            minconf,
+            include_watchonly,
+            as_of_height,
+        )
+    }
+
+    async fn list_since_block(
+        &self,
+        blockhash: Option<&str>,
+        target_confirmations: Option<u32>,
+    ) -> list_since_block::Response {
+        list_since_block::call(
+            self.wallet().await?.as_ref(),
+            blockhash,
+            target_confirmations,
+        )
+    }
+
+    async fn rescan_transaction(&self, txid: &str) -> rescan_transaction::Response {
+        let client = self.lightwalletd()?;
+        let res = rescan_transaction::call(self.wallet().await?.as_mut(), client, txid).await;
+        if res.is_ok() {
+            self.wallet.wake_sync();
+        }
+        res
+    }
+
+    async fn rescan_blockchain(&self, start_height: Option<u32>) -> rescan_blockchain::Response {
+        let res = rescan_blockchain::call(self.wallet().await?.as_mut(), start_height);
+        if res.is_ok() {
+            self.wallet.wake_sync();
+        }
+        res
+    }
+
+    async fn list_transactions(
+        &self,
+        account: Option<&str>,
+        count: Option<u32>,
+        from: Option<u32>,
+    ) -> list_transactions::Response {
+        list_transactions::call(self.wallet().await?.as_ref(), account, count, from)
+    }
+
+    async fn export_viewing_key(&self, account: &str) -> export_viewing_key::Response {
+        export_viewing_key::call(self.wallet().await?.as_ref(), account)
+    }
+
+    async fn import_viewing_key(
+        &self,
+        vkey: &str,
+        start_height: Option<u32>,
+        skip_rescan: Option<bool>,
+    ) -> import_viewing_key::Response {
+        let client = self.lightwalletd()?;
+        let res = import_viewing_key::call(
+            self.wallet().await?.as_mut(),
+            client,
+            vkey,
+            start_height,
+            skip_rescan,
+        )
+        .await;
+        if res.is_ok() && !skip_rescan.unwrap_or(false) {
+            self.wallet.wake_sync();
+        }
+        res
+    }
+
+    async fn get_received_by_address(
+        &self,
+        address: &str,
+        minconf: Option<u32>,
+    ) -> get_received_by_address::Response {
+        get_received_by_address::call(self.wallet().await?.as_ref(), address, minconf)
+    }
+
+    async fn list_received_by_address(
+        &self,
+        minconf: Option<u32>,
+        include_empty: Option<bool>,
+        address_filter: Option<&str>,
+    ) -> list_received_by_address::Response {
+        list_received_by_address::call(
+            self.wallet().await?.as_ref(),
+            minconf,
+            include_empty,
+            address_filter,
+        )
+    }
+
+    async fn get_account_activity(
+        &self,
+        account: &str,
+        from_height: u32,
+        to_height: Option<u32>,
+    ) -> get_account_activity::Response {
+        get_account_activity::call(
+            self.wallet().await?.as_ref(),
+            account,
+            from_height,
+            to_height,
+        )
+    }
+
+    async fn get_address_for_account(
+        &self,
+        account: &str,
+        receiver_types: Option<Vec<String>>,
+        diversifier_index: Option<u128>,
+    ) -> get_address_for_account::Response {
+        get_address_for_account::call(
+            self.wallet().await?.as_mut(),
+            account,
+            receiver_types,
+            diversifier_index,
+        )
+    }
 }
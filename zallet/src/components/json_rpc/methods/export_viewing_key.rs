@@ -0,0 +1,62 @@
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zcash_client_backend::data_api::{Account as _, WalletRead};
+use zcash_client_sqlite::AccountUuid;
+
+use crate::components::{json_rpc::server::LegacyCode, wallet::WalletConnection};
+
+/// Response to a `z_exportviewingkey` RPC request.
+pub(crate) type Response = RpcResult<ViewingKey>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct ViewingKey {
+    /// The account's unified full viewing key, encoded for the wallet's network.
+    ufvk: String,
+
+    /// The fingerprint of the seed the account was derived from, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seedfp: Option<String>,
+
+    /// The ZIP 32 account ID, if the account was derived from a known seed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account: Option<u64>,
+}
+
+pub(crate) fn call(wallet: &WalletConnection, account: &str) -> Response {
+    let account_id = Uuid::parse_str(account)
+        .map(AccountUuid::from_uuid)
+        .map_err(|_| {
+            RpcError::borrowed(
+                LegacyCode::InvalidParameter.into(),
+                "account must be an account UUID",
+                None,
+            )
+        })?;
+
+    let account = wallet
+        .get_account(account_id)
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::get_account failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .ok_or_else(|| {
+            RpcError::borrowed(LegacyCode::InvalidParameter.into(), "Unknown account", None)
+        })?;
+
+    // Every account in a `zcash_client_sqlite` wallet has a UFVK.
+    let ufvk = account.ufvk().ok_or(RpcErrorCode::InternalError)?;
+    let derivation = account.source().key_derivation();
+
+    Ok(ViewingKey {
+        ufvk: ufvk.encode(wallet.params()),
+        seedfp: derivation.map(|derivation| hex::encode(derivation.seed_fingerprint().to_bytes())),
+        account: derivation.map(|derivation| u32::from(derivation.account_index()).into()),
+    })
+}
@@ -0,0 +1,246 @@
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use rusqlite::named_params;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zcash_client_backend::data_api::WalletRead;
+use zcash_client_sqlite::AccountUuid;
+use zcash_protocol::value::Zatoshis;
+
+use crate::components::{
+    json_rpc::{server::LegacyCode, value_from_zatoshis},
+    wallet::WalletConnection,
+};
+
+/// Response to a `z_getaccountactivity` RPC request.
+pub(crate) type Response = RpcResult<AccountActivity>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct AccountActivity {
+    /// The UUID of the account.
+    account_uuid: String,
+
+    /// The first block height included in the summary.
+    from_height: u32,
+
+    /// The block height at which the summary ends (exclusive).
+    to_height: u32,
+
+    /// The number of mined transactions in the range that involve the account.
+    tx_count: u64,
+
+    /// The total increase in the account's balance, summed over the transactions that
+    /// increased it, in ZEC.
+    received: f64,
+
+    /// The total decrease in the account's balance (including fees), summed over the
+    /// transactions that decreased it, in ZEC.
+    sent: f64,
+
+    /// The total fees paid by the account, in ZEC.
+    ///
+    /// This only includes fees that the wallet knows.
+    fees: f64,
+
+    /// A breakdown by value pool of the outputs received and sent by the account.
+    pools: Pools,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct Pools {
+    transparent: PoolActivity,
+    sapling: PoolActivity,
+    orchard: PoolActivity,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct PoolActivity {
+    /// The total value of outputs in this pool received by the account from outside it,
+    /// in ZEC.
+    received: f64,
+
+    /// The total value of outputs in this pool sent by the account to recipients outside
+    /// it, in ZEC.
+    sent: f64,
+}
+
+pub(crate) fn call(
+    wallet: &WalletConnection,
+    account: &str,
+    from_height: u32,
+    to_height: Option<u32>,
+) -> Response {
+    let account_id = Uuid::parse_str(account)
+        .map(AccountUuid::from_uuid)
+        .map_err(|_| {
+            RpcError::borrowed(
+                LegacyCode::InvalidParameter.into(),
+                "account must be an account UUID",
+                None,
+            )
+        })?;
+
+    if wallet
+        .get_account(account_id)
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::get_account failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .is_none()
+    {
+        return Err(RpcError::borrowed(
+            LegacyCode::InvalidParameter.into(),
+            "Unknown account",
+            None,
+        ));
+    }
+
+    // By default, the range ends after the current chain tip.
+    let to_height = match to_height {
+        Some(height) => height,
+        None => wallet
+            .chain_height()
+            .map_err(|e| {
+                RpcError::owned(
+                    LegacyCode::Database.into(),
+                    "WalletDb::chain_height failed",
+                    Some(format!("{e}")),
+                )
+            })?
+            .map_or(0, |height| u32::from(height) + 1),
+    };
+    if from_height > to_height {
+        return Err(RpcError::borrowed(
+            LegacyCode::InvalidParameter.into(),
+            "from_height must not be greater than to_height",
+            None,
+        ));
+    }
+
+    let account_uuid = &account_id.expose_uuid().as_bytes()[..];
+
+    let (tx_count, received, sent, fees) = wallet
+        .with_raw(|conn| {
+            conn.query_row(
+                "SELECT
+                    COUNT(*) AS tx_count,
+                    COALESCE(SUM(
+                        CASE WHEN account_balance_delta > 0 THEN account_balance_delta END
+                    ), 0) AS received,
+                    COALESCE(SUM(
+                        CASE WHEN account_balance_delta < 0 THEN -account_balance_delta END
+                    ), 0) AS sent,
+                    COALESCE(SUM(
+                        CASE WHEN account_balance_delta < 0 THEN fee_paid END
+                    ), 0) AS fees
+                FROM v_transactions
+                WHERE account_uuid = :account_uuid
+                AND mined_height >= :from_height
+                AND mined_height < :to_height",
+                named_params! {
+                    ":account_uuid": account_uuid,
+                    ":from_height": from_height,
+                    ":to_height": to_height,
+                },
+                |row| {
+                    Ok((
+                        row.get::<_, u64>("tx_count")?,
+                        row.get::<_, u64>("received")?,
+                        row.get::<_, u64>("sent")?,
+                        row.get::<_, u64>("fees")?,
+                    ))
+                },
+            )
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query v_transactions",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    let pool_rows = wallet
+        .with_raw(|conn| {
+            conn.prepare(
+                "SELECT
+                    o.output_pool,
+                    COALESCE(SUM(
+                        CASE
+                        WHEN o.to_account_uuid = :account_uuid
+                        AND (o.from_account_uuid IS NULL OR o.from_account_uuid != :account_uuid)
+                        THEN o.value
+                        END
+                    ), 0) AS received,
+                    COALESCE(SUM(
+                        CASE
+                        WHEN o.from_account_uuid = :account_uuid
+                        AND (o.to_account_uuid IS NULL OR o.to_account_uuid != :account_uuid)
+                        THEN o.value
+                        END
+                    ), 0) AS sent
+                FROM v_tx_outputs o
+                JOIN transactions t ON t.txid = o.txid
+                WHERE t.mined_height >= :from_height
+                AND t.mined_height < :to_height
+                GROUP BY o.output_pool",
+            )?
+            .query_map(
+                named_params! {
+                    ":account_uuid": account_uuid,
+                    ":from_height": from_height,
+                    ":to_height": to_height,
+                },
+                |row| {
+                    Ok((
+                        row.get::<_, i64>("output_pool")?,
+                        row.get::<_, u64>("received")?,
+                        row.get::<_, u64>("sent")?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query v_tx_outputs",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    let mut pools = Pools::default();
+    for (output_pool, received, sent) in pool_rows {
+        // Pool codes used by `zcash_client_sqlite`.
+        let pool = match output_pool {
+            0 => &mut pools.transparent,
+            2 => &mut pools.sapling,
+            3 => &mut pools.orchard,
+            _ => return Err(RpcErrorCode::InternalError.into()),
+        };
+        pool.received = value(received)?;
+        pool.sent = value(sent)?;
+    }
+
+    Ok(AccountActivity {
+        account_uuid: account_id.expose_uuid().to_string(),
+        from_height,
+        to_height,
+        tx_count,
+        received: value(received)?,
+        sent: value(sent)?,
+        fees: value(fees)?,
+        pools,
+    })
+}
+
+fn value(zats: u64) -> RpcResult<f64> {
+    Zatoshis::from_u64(zats)
+        .map(value_from_zatoshis)
+        .map_err(|_| RpcErrorCode::InternalError.into())
+}
@@ -0,0 +1,227 @@
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use rusqlite::{named_params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zcash_client_backend::{
+    address::UnifiedAddress,
+    data_api::{Account as _, WalletRead, WalletWrite},
+    keys::UnifiedAddressRequest,
+};
+use zcash_client_sqlite::AccountUuid;
+use zip32::DiversifierIndex;
+
+use crate::components::{json_rpc::server::LegacyCode, wallet::WalletConnection};
+
+/// Response to a `z_getaddressforaccount` RPC request.
+pub(crate) type Response = RpcResult<Address>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct Address {
+    /// The UUID of the account.
+    account_uuid: String,
+
+    /// The diversifier index of the address.
+    diversifier_index: u128,
+
+    /// The receiver types included in the address.
+    receiver_types: Vec<String>,
+
+    /// The unified address.
+    address: String,
+}
+
+/// The maximum diversifier index (diversifier indices are 88 bits).
+const MAX_DIVERSIFIER_INDEX: u128 = (1 << 88) - 1;
+
+pub(crate) fn call(
+    wallet: &mut WalletConnection,
+    account: &str,
+    receiver_types: Option<Vec<String>>,
+    diversifier_index: Option<u128>,
+) -> Response {
+    let account_id = Uuid::parse_str(account)
+        .map(AccountUuid::from_uuid)
+        .map_err(|_| {
+            RpcError::borrowed(
+                LegacyCode::InvalidParameter.into(),
+                "account must be an account UUID",
+                None,
+            )
+        })?;
+
+    let account = wallet
+        .get_account(account_id)
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::get_account failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .ok_or_else(|| {
+            RpcError::borrowed(LegacyCode::InvalidParameter.into(), "Unknown account", None)
+        })?;
+
+    // Every account in a `zcash_client_sqlite` wallet has a UFVK.
+    let ufvk = account.ufvk().ok_or(RpcErrorCode::InternalError)?;
+
+    // By default, include every receiver type that the account supports.
+    let (p2pkh, sapling, orchard) = match receiver_types {
+        None => (
+            ufvk.transparent().is_some(),
+            ufvk.sapling().is_some(),
+            ufvk.orchard().is_some(),
+        ),
+        Some(receiver_types) => {
+            let (mut p2pkh, mut sapling, mut orchard) = (false, false, false);
+            for receiver_type in &receiver_types {
+                let (requested, supported) = match receiver_type.as_str() {
+                    "p2pkh" => (&mut p2pkh, ufvk.transparent().is_some()),
+                    "sapling" => (&mut sapling, ufvk.sapling().is_some()),
+                    "orchard" => (&mut orchard, ufvk.orchard().is_some()),
+                    _ => {
+                        return Err(RpcError::owned(
+                            LegacyCode::InvalidParameter.into(),
+                            format!("Unsupported receiver type: {receiver_type}"),
+                            None::<()>,
+                        ))
+                    }
+                };
+                if !supported {
+                    return Err(RpcError::owned(
+                        LegacyCode::InvalidParameter.into(),
+                        format!(
+                            "The account's viewing key does not support receiver type: {receiver_type}"
+                        ),
+                        None::<()>,
+                    ));
+                }
+                *requested = true;
+            }
+            (p2pkh, sapling, orchard)
+        }
+    };
+
+    let request = UnifiedAddressRequest::new(orchard, sapling, p2pkh).ok_or_else(|| {
+        RpcError::borrowed(
+            LegacyCode::InvalidParameter.into(),
+            "At least one shielded receiver type is required",
+            None,
+        )
+    })?;
+
+    let (address, diversifier_index) = match diversifier_index {
+        None => {
+            let address = wallet
+                .get_next_available_address(account_id, Some(request))
+                .map_err(|e| {
+                    RpcError::owned(
+                        LegacyCode::Database.into(),
+                        "WalletDb::get_next_available_address failed",
+                        Some(format!("{e}")),
+                    )
+                })?
+                // This would be a race condition between this and account deletion.
+                .ok_or(RpcErrorCode::InternalError)?;
+
+            let diversifier_index = stored_diversifier_index(wallet, account_id, &address)?
+                .ok_or(RpcErrorCode::InternalError)?;
+
+            (address, diversifier_index)
+        }
+        Some(index) => {
+            if index > MAX_DIVERSIFIER_INDEX {
+                return Err(RpcError::borrowed(
+                    LegacyCode::InvalidParameter.into(),
+                    "diversifier_index must be less than 2^88",
+                    None,
+                ));
+            }
+
+            let mut bytes = [0; 11];
+            bytes.copy_from_slice(&index.to_le_bytes()[..11]);
+            let address = ufvk
+                .address(DiversifierIndex::from(bytes), request)
+                .map_err(|e| {
+                    RpcError::owned(
+                        LegacyCode::InvalidParameter.into(),
+                        "Cannot generate an address at the given diversifier index",
+                        Some(format!("{e:?}")),
+                    )
+                })?;
+
+            // The wallet detects shielded receivers at any diversifier index, but only
+            // watches transparent receivers for addresses it has generated itself.
+            if p2pkh && stored_diversifier_index(wallet, account_id, &address)?.is_none() {
+                return Err(RpcError::borrowed(
+                    LegacyCode::InvalidParameter.into(),
+                    "p2pkh receivers are only supported at diversifier indices the wallet has generated",
+                    None,
+                ));
+            }
+
+            (address, index)
+        }
+    };
+
+    let receiver_types = [
+        (address.has_transparent(), "p2pkh"),
+        (address.has_sapling(), "sapling"),
+        (address.has_orchard(), "orchard"),
+    ]
+    .into_iter()
+    .filter_map(|(included, receiver_type)| included.then(|| receiver_type.into()))
+    .collect();
+
+    Ok(Address {
+        account_uuid: account_id.expose_uuid().to_string(),
+        diversifier_index,
+        receiver_types,
+        address: address.encode(wallet.params()),
+    })
+}
+
+/// Returns the diversifier index at which the wallet generated the given address, if it
+/// has.
+fn stored_diversifier_index(
+    wallet: &WalletConnection,
+    account_id: AccountUuid,
+    address: &UnifiedAddress,
+) -> RpcResult<Option<u128>> {
+    wallet
+        .with_raw(|conn| {
+            conn.query_row(
+                "SELECT addresses.diversifier_index_be
+                FROM addresses
+                JOIN accounts ON accounts.id = addresses.account_id
+                WHERE accounts.uuid = :account_uuid
+                AND addresses.address = :address",
+                named_params! {
+                    ":account_uuid": &account_id.expose_uuid().as_bytes()[..],
+                    ":address": address.encode(wallet.params()),
+                },
+                |row| row.get::<_, Vec<u8>>("diversifier_index_be"),
+            )
+            .optional()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query addresses",
+                Some(format!("{e}")),
+            )
+        })?
+        .map(|index_be| {
+            // Diversifier indices are stored as 11 big-endian bytes.
+            if index_be.len() > 16 {
+                return Err(RpcErrorCode::InternalError.into());
+            }
+            let mut bytes = [0; 16];
+            bytes[16 - index_be.len()..].copy_from_slice(&index_be);
+            Ok(u128::from_be_bytes(bytes))
+        })
+        .transpose()
+}
@@ -0,0 +1,13 @@
+use jsonrpsee::core::RpcResult;
+use tonic::transport::Channel;
+use zcash_client_backend::proto::service::compact_tx_streamer_client::CompactTxStreamerClient;
+
+use super::{get_block_count::chain_tip_height, get_block_hash::block_hash_at};
+
+/// Response to a `getbestblockhash` RPC request.
+pub(crate) type Response = RpcResult<String>;
+
+pub(crate) async fn call(mut client: CompactTxStreamerClient<Channel>) -> Response {
+    let height = chain_tip_height(&mut client).await?;
+    block_hash_at(&mut client, height).await
+}
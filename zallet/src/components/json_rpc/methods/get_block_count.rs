@@ -0,0 +1,39 @@
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned as RpcError};
+use tonic::transport::Channel;
+use zcash_client_backend::proto::service::{
+    compact_tx_streamer_client::CompactTxStreamerClient, ChainSpec,
+};
+
+use crate::components::json_rpc::server::LegacyCode;
+
+/// Response to a `getblockcount` RPC request.
+pub(crate) type Response = RpcResult<u32>;
+
+pub(crate) async fn call(mut client: CompactTxStreamerClient<Channel>) -> Response {
+    chain_tip_height(&mut client).await
+}
+
+/// Returns the height of the chain tip, as reported by `lightwalletd`.
+pub(super) async fn chain_tip_height(
+    client: &mut CompactTxStreamerClient<Channel>,
+) -> RpcResult<u32> {
+    let tip = client
+        .get_latest_block(ChainSpec {})
+        .await
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Misc.into(),
+                "Failed to fetch the chain tip from lightwalletd",
+                Some(e.message().to_string()),
+            )
+        })?
+        .into_inner();
+
+    u32::try_from(tip.height).map_err(|_| {
+        RpcError::borrowed(
+            LegacyCode::Misc.into(),
+            "lightwalletd reported an invalid chain tip height",
+            None,
+        )
+    })
+}
@@ -0,0 +1,60 @@
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned as RpcError};
+use tonic::transport::Channel;
+use zcash_client_backend::proto::service::{
+    compact_tx_streamer_client::CompactTxStreamerClient, BlockId,
+};
+
+use crate::components::json_rpc::server::LegacyCode;
+
+use super::get_block_count::chain_tip_height;
+
+/// Response to a `getblockhash` RPC request.
+pub(crate) type Response = RpcResult<String>;
+
+pub(crate) async fn call(mut client: CompactTxStreamerClient<Channel>, index: i64) -> Response {
+    let tip_height = chain_tip_height(&mut client).await?;
+
+    // Like `zcashd`, negative heights count back from the chain tip (so -1 is the tip).
+    let height = if index < 0 {
+        i64::from(tip_height) + 1 + index
+    } else {
+        index
+    };
+
+    let height = u32::try_from(height)
+        .ok()
+        .filter(|height| *height <= tip_height)
+        .ok_or_else(|| {
+            RpcError::borrowed(
+                LegacyCode::InvalidParameter.into(),
+                "Block height out of range",
+                None,
+            )
+        })?;
+
+    block_hash_at(&mut client, height).await
+}
+
+/// Returns the hash of the block at the given height in the main chain, as reported by
+/// `lightwalletd`.
+pub(super) async fn block_hash_at(
+    client: &mut CompactTxStreamerClient<Channel>,
+    height: u32,
+) -> RpcResult<String> {
+    let block = client
+        .get_block(BlockId {
+            height: height.into(),
+            hash: vec![],
+        })
+        .await
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Misc.into(),
+                "Failed to fetch block from lightwalletd",
+                Some(e.message().to_string()),
+            )
+        })?
+        .into_inner();
+
+    Ok(block.hash().to_string())
+}
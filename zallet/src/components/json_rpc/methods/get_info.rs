@@ -0,0 +1,158 @@
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned as RpcError};
+use serde::{Deserialize, Serialize};
+use tonic::transport::Channel;
+use zcash_client_backend::{
+    data_api::WalletRead,
+    proto::service::{compact_tx_streamer_client::CompactTxStreamerClient, Empty},
+};
+use zcash_protocol::consensus::{BlockHeight, BranchId, NetworkUpgrade, Parameters};
+
+use crate::{
+    components::{json_rpc::server::LegacyCode, wallet::WalletConnection},
+    network::{self, kind, KNOWN_UPGRADES},
+};
+
+/// Response to a `getinfo` RPC request.
+pub(crate) type Response = RpcResult<GetInfo>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct GetInfo {
+    /// The version of Zallet.
+    version: String,
+
+    /// The network that the wallet is configured for.
+    ///
+    /// One of `["main", "test", "regtest"]`.
+    network: String,
+
+    /// The `lightwalletd` server that the wallet syncs with.
+    lightwalletd: String,
+
+    /// Whether Zallet can currently reach the `lightwalletd` server.
+    connected: bool,
+
+    /// The height of the chain tip, as reported by `lightwalletd`.
+    ///
+    /// Omitted if `lightwalletd` is unreachable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blocks: Option<u32>,
+
+    /// The consensus branch ID that this version of Zallet expects at the chain tip, in
+    /// hex.
+    ///
+    /// Omitted if `lightwalletd` is unreachable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    consensus_branch_id: Option<String>,
+
+    /// Whether the wallet has scanned every block up to the chain tip.
+    synced: bool,
+
+    /// The network upgrades that this version of Zallet knows about, in activation
+    /// order.
+    upgrades: Vec<Upgrade>,
+
+    /// The next network upgrade in this version of Zallet's schedule after the chain tip.
+    ///
+    /// Omitted if `lightwalletd` is unreachable, or if no upgrade is scheduled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upcoming_upgrade: Option<Upgrade>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Upgrade {
+    /// The name of the network upgrade.
+    name: String,
+
+    /// The upgrade's consensus branch ID, in hex.
+    consensus_branch_id: String,
+
+    /// The height at which the upgrade activates on the configured network.
+    ///
+    /// Omitted if the upgrade has no activation height on this network.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    activation_height: Option<u32>,
+}
+
+impl Upgrade {
+    fn new(
+        upgrade: NetworkUpgrade,
+        branch_id: BranchId,
+        activation_height: Option<BlockHeight>,
+    ) -> Self {
+        Self {
+            name: upgrade.to_string(),
+            consensus_branch_id: format!("{:08x}", u32::from(branch_id)),
+            activation_height: activation_height.map(u32::from),
+        }
+    }
+}
+
+pub(crate) async fn call(
+    wallet: &WalletConnection,
+    lightwalletd: String,
+    client: Option<CompactTxStreamerClient<Channel>>,
+) -> Response {
+    let chain_tip = match client {
+        Some(mut client) => client
+            .get_lightd_info(Empty {})
+            .await
+            .ok()
+            .and_then(|info| u32::try_from(info.into_inner().block_height).ok()),
+        None => None,
+    };
+
+    let fully_scanned_height = wallet
+        .block_fully_scanned()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::block_fully_scanned failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .map(|block| u32::from(block.block_height()));
+
+    let consensus_branch_id = chain_tip.map(|height| {
+        format!(
+            "{:08x}",
+            u32::from(BranchId::for_height(
+                wallet.params(),
+                BlockHeight::from_u32(height)
+            ))
+        )
+    });
+
+    let synced = match (chain_tip, fully_scanned_height) {
+        (Some(chain_tip), Some(scanned)) => scanned >= chain_tip,
+        _ => false,
+    };
+
+    let upgrades = KNOWN_UPGRADES
+        .iter()
+        .map(|&(upgrade, branch_id)| {
+            Upgrade::new(
+                upgrade,
+                branch_id,
+                wallet.params().activation_height(upgrade),
+            )
+        })
+        .collect();
+
+    let upcoming_upgrade = chain_tip
+        .and_then(|height| network::next_upgrade(wallet.params(), BlockHeight::from_u32(height)))
+        .map(|(upgrade, branch_id, activation_height)| {
+            Upgrade::new(upgrade, branch_id, Some(activation_height))
+        });
+
+    Ok(GetInfo {
+        version: env!("CARGO_PKG_VERSION").into(),
+        network: kind::name(&wallet.params().network_type()).into(),
+        lightwalletd,
+        connected: chain_tip.is_some(),
+        blocks: chain_tip,
+        consensus_branch_id,
+        synced,
+        upgrades,
+        upcoming_upgrade,
+    })
+}
@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use rusqlite::named_params;
+use serde::{Deserialize, Serialize};
+use zcash_client_backend::data_api::WalletRead;
+use zcash_protocol::value::{Zatoshis, COIN};
+
+use crate::{
+    components::{
+        json_rpc::{server::LegacyCode, value_from_zatoshis},
+        wallet::WalletConnection,
+    },
+    config::{BuilderSection, LimitsSection},
+};
+
+/// Response to a `z_getnotestatistics` RPC request.
+pub(crate) type Response = RpcResult<Vec<AccountStatistics>>;
+
+/// Upper bounds (exclusive) of the note value histogram buckets, in zatoshis. The last
+/// bucket is unbounded.
+const HISTOGRAM_BOUNDS: &[u64] = &[
+    COIN / 1000,
+    COIN / 100,
+    COIN / 10,
+    COIN,
+    10 * COIN,
+    100 * COIN,
+];
+
+/// Payment sizes, in zatoshis, for which we report how many notes would be required.
+const PAYMENT_SIZES: &[u64] = &[COIN, 10 * COIN];
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct AccountStatistics {
+    /// The account's UUID within this Zallet instance.
+    account_uuid: String,
+
+    /// Statistics for unspent Sapling notes.
+    sapling: PoolStatistics,
+
+    /// Statistics for unspent Orchard notes.
+    orchard: PoolStatistics,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct PoolStatistics {
+    /// The number of unspent notes.
+    note_count: u64,
+
+    /// The total value of unspent notes, in ZEC.
+    total_value: f64,
+
+    /// The value of the largest unspent note, in ZEC.
+    ///
+    /// Omitted if there are no unspent notes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    largest_note: Option<f64>,
+
+    /// The distribution of unspent note values.
+    histogram: Vec<HistogramBucket>,
+
+    /// The minimum number of notes required to cover common payment sizes.
+    notes_to_cover: Vec<PaymentCoverage>,
+
+    /// An estimate of the cost of merging all non-dust notes into a single note.
+    consolidation: Consolidation,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct HistogramBucket {
+    /// The (inclusive) lower bound of the bucket, in ZEC.
+    min: f64,
+
+    /// The (exclusive) upper bound of the bucket, in ZEC.
+    ///
+    /// Omitted for the last bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+
+    /// The number of notes with values in this bucket.
+    count: u64,
+
+    /// The total value of notes in this bucket, in ZEC.
+    value: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct PaymentCoverage {
+    /// The payment size, in ZEC.
+    amount: f64,
+
+    /// The minimum number of notes whose values sum to at least `amount`, ignoring fees.
+    ///
+    /// Omitted if the pool's total value is less than `amount`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct Consolidation {
+    /// The number of notes that are worth spending (their value exceeds the configured
+    /// marginal fee).
+    notes: u64,
+
+    /// The number of transactions required, given the configured action limit.
+    transactions: u64,
+
+    /// The total ZIP 317 fee for those transactions, in ZEC.
+    fee: f64,
+}
+
+/// The wallet DB tables that hold a shielded pool's notes.
+struct PoolTables {
+    notes: &'static str,
+    spends: &'static str,
+    note_id: &'static str,
+}
+
+const SAPLING: PoolTables = PoolTables {
+    notes: "sapling_received_notes",
+    spends: "sapling_received_note_spends",
+    note_id: "sapling_received_note_id",
+};
+
+const ORCHARD: PoolTables = PoolTables {
+    notes: "orchard_received_notes",
+    spends: "orchard_received_note_spends",
+    note_id: "orchard_received_note_id",
+};
+
+pub(crate) fn call(
+    wallet: &WalletConnection,
+    builder: &BuilderSection,
+    limits: &LimitsSection,
+) -> Response {
+    let chain_height = wallet
+        .chain_height()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::chain_height failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .map_or(0, u32::from);
+
+    let mut sapling = pool_statistics(wallet, builder, &SAPLING, chain_height)?;
+    let mut orchard = pool_statistics(wallet, builder, &ORCHARD, chain_height)?;
+
+    // Sapling has no configured action limit, so we assume one merge transaction.
+    for stats in sapling.values_mut() {
+        stats.consolidation = estimate_consolidation(builder, stats.consolidation.notes, None)?;
+    }
+    for stats in orchard.values_mut() {
+        stats.consolidation = estimate_consolidation(
+            builder,
+            stats.consolidation.notes,
+            Some(limits.orchard_actions().into()),
+        )?;
+    }
+
+    let mut accounts = vec![];
+    for account_id in wallet
+        .get_account_ids()
+        .map_err(|_| RpcErrorCode::from(LegacyCode::Database))?
+    {
+        let uuid = account_id.expose_uuid();
+        let key = uuid.as_bytes().to_vec();
+
+        accounts.push(AccountStatistics {
+            account_uuid: uuid.to_string(),
+            sapling: sapling.remove(&key).unwrap_or_else(PoolStatistics::empty),
+            orchard: orchard.remove(&key).unwrap_or_else(PoolStatistics::empty),
+        });
+    }
+
+    Ok(accounts)
+}
+
+/// Computes statistics for the unspent notes in a single pool, keyed by account UUID.
+///
+/// Aggregation is done in SQL so that we never load every note into memory.
+fn pool_statistics(
+    wallet: &WalletConnection,
+    builder: &BuilderSection,
+    tables: &PoolTables,
+    chain_height: u32,
+) -> RpcResult<HashMap<Vec<u8>, PoolStatistics>> {
+    // Notes that have been mined, and are not spent by a transaction that is mined or
+    // could still be mined.
+    let unspent_notes = format!(
+        "SELECT accounts.uuid AS account_uuid, rn.value AS value
+        FROM {notes} rn
+        JOIN accounts ON accounts.id = rn.account_id
+        JOIN transactions t ON t.id_tx = rn.tx
+        WHERE t.mined_height IS NOT NULL
+        AND rn.id NOT IN (
+            SELECT {note_id}
+            FROM {spends}
+            JOIN transactions stx ON stx.id_tx = transaction_id
+            WHERE stx.mined_height IS NOT NULL
+            OR stx.expiry_height IS NULL
+            OR stx.expiry_height = 0
+            OR stx.expiry_height > :chain_height
+        )",
+        notes = tables.notes,
+        spends = tables.spends,
+        note_id = tables.note_id,
+    );
+
+    let bucket_expr = HISTOGRAM_BOUNDS
+        .iter()
+        .enumerate()
+        .fold(String::from("CASE"), |expr, (i, bound)| {
+            format!("{expr} WHEN value < {bound} THEN {i}")
+        })
+        + &format!(" ELSE {} END", HISTOGRAM_BOUNDS.len());
+
+    let db_err = |e: rusqlite::Error| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "Failed to query note statistics",
+            Some(format!("{e}")),
+        )
+    };
+
+    let mut stats = HashMap::<Vec<u8>, PoolStatistics>::new();
+
+    let buckets = wallet
+        .with_raw(|conn| {
+            conn.prepare(&format!(
+                "SELECT account_uuid, {bucket_expr} AS bucket,
+                        COUNT(*) AS note_count, SUM(value) AS total_value,
+                        MAX(value) AS max_value,
+                        SUM(value > :dust_threshold) AS non_dust_count
+                FROM ({unspent_notes})
+                GROUP BY account_uuid, bucket"
+            ))?
+            .query_map(
+                named_params! {
+                    ":chain_height": chain_height,
+                    ":dust_threshold": builder.fee_marginal_zatoshis(),
+                },
+                |row| {
+                    Ok((
+                        row.get::<_, Vec<u8>>("account_uuid")?,
+                        row.get::<_, usize>("bucket")?,
+                        row.get::<_, u64>("note_count")?,
+                        row.get::<_, u64>("total_value")?,
+                        row.get::<_, u64>("max_value")?,
+                        row.get::<_, u64>("non_dust_count")?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(db_err)?;
+
+    let mut totals = HashMap::<Vec<u8>, (u64, u64)>::new();
+    for (account_uuid, bucket, count, value, max_value, non_dust) in buckets {
+        let stats = stats
+            .entry(account_uuid.clone())
+            .or_insert_with(PoolStatistics::empty);
+        let (total, largest) = totals.entry(account_uuid).or_default();
+
+        stats.note_count += count;
+        stats.consolidation.notes += non_dust;
+        stats.histogram[bucket].count = count;
+        stats.histogram[bucket].value = value_from_zat(value)?;
+        *total += value;
+        *largest = (*largest).max(max_value);
+    }
+
+    for (account_uuid, (total, largest)) in &totals {
+        let stats = stats.get_mut(account_uuid).expect("inserted above");
+        stats.total_value = value_from_zat(*total)?;
+        stats.largest_note = Some(value_from_zat(*largest)?);
+    }
+
+    // Greedily selecting the largest notes first gives the minimum number of notes
+    // needed to reach each payment size.
+    for (i, &amount) in PAYMENT_SIZES.iter().enumerate() {
+        let counts = wallet
+            .with_raw(|conn| {
+                conn.prepare(&format!(
+                    "SELECT account_uuid, COUNT(*) AS notes
+                    FROM (
+                        SELECT account_uuid, value,
+                               SUM(value) OVER (
+                                   PARTITION BY account_uuid
+                                   ORDER BY value DESC
+                                   ROWS UNBOUNDED PRECEDING
+                               ) AS running_total
+                        FROM ({unspent_notes})
+                    )
+                    WHERE running_total - value < :amount
+                    GROUP BY account_uuid"
+                ))?
+                .query_map(
+                    named_params! {
+                        ":chain_height": chain_height,
+                        ":amount": amount,
+                    },
+                    |row| {
+                        Ok((
+                            row.get::<_, Vec<u8>>("account_uuid")?,
+                            row.get::<_, u64>("notes")?,
+                        ))
+                    },
+                )?
+                .collect::<Result<HashMap<_, _>, _>>()
+            })
+            .map_err(db_err)?;
+
+        for (account_uuid, stats) in stats.iter_mut() {
+            let coverable = totals
+                .get(account_uuid)
+                .map_or(false, |(total, _)| *total >= amount);
+
+            stats.notes_to_cover[i].notes = counts.get(account_uuid).copied().filter(|_| coverable);
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Estimates the ZIP 317 fee (with the configured fee parameters) for merging `notes`
+/// notes into a single note within the same pool.
+///
+/// If `action_limit` is set, notes are merged in rounds of transactions that each spend
+/// at most `action_limit` notes, until a single note remains.
+fn estimate_consolidation(
+    builder: &BuilderSection,
+    notes: u64,
+    action_limit: Option<u64>,
+) -> RpcResult<Consolidation> {
+    // A single spend and output per transaction can't reduce the number of notes.
+    let per_tx = action_limit.map_or(notes, |limit| limit.max(2));
+
+    let mut remaining = notes;
+    let mut transactions = 0;
+    let mut fee = 0;
+    while remaining > 1 {
+        let round = remaining.div_ceil(per_tx);
+        for i in 0..round {
+            let spends = per_tx.min(remaining - i * per_tx);
+            // Each transaction has one output, so its logical actions are its spends.
+            fee += builder.fee_for_logical_actions(spends as usize);
+        }
+        transactions += round;
+        remaining = round;
+    }
+
+    Ok(Consolidation {
+        notes,
+        transactions,
+        fee: value_from_zat(fee)?,
+    })
+}
+
+fn value_from_zat(value: u64) -> RpcResult<f64> {
+    Zatoshis::from_u64(value)
+        .map(value_from_zatoshis)
+        .map_err(|_| RpcErrorCode::InternalError.into())
+}
+
+impl PoolStatistics {
+    fn empty() -> Self {
+        let histogram = (0..=HISTOGRAM_BOUNDS.len())
+            .map(|i| HistogramBucket {
+                min: i
+                    .checked_sub(1)
+                    .map_or(0.0, |j| HISTOGRAM_BOUNDS[j] as f64 / COIN as f64),
+                max: HISTOGRAM_BOUNDS
+                    .get(i)
+                    .map(|bound| *bound as f64 / COIN as f64),
+                count: 0,
+                value: 0.0,
+            })
+            .collect();
+
+        Self {
+            histogram,
+            notes_to_cover: PAYMENT_SIZES
+                .iter()
+                .map(|amount| PaymentCoverage {
+                    amount: *amount as f64 / COIN as f64,
+                    notes: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
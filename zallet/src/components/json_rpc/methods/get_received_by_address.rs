@@ -0,0 +1,26 @@
+use jsonrpsee::{core::RpcResult, types::ErrorCode as RpcErrorCode};
+use zcash_protocol::value::Zatoshis;
+
+use crate::components::{json_rpc::value_from_zatoshis, wallet::WalletConnection};
+
+use super::list_received_by_address::{
+    parse_wallet_address, received_by_address, wallet_transparent_addresses,
+};
+
+/// Response to a `getreceivedbyaddress` RPC request.
+pub(crate) type Response = RpcResult<f64>;
+
+pub(crate) fn call(wallet: &WalletConnection, address: &str, minconf: Option<u32>) -> Response {
+    let minconf = minconf.unwrap_or(1);
+
+    let addresses = wallet_transparent_addresses(wallet)?;
+    let address = parse_wallet_address(wallet, &addresses, address)?;
+
+    let value = received_by_address(wallet, minconf, Some(&address))?
+        .remove(&address)
+        .map_or(0, |received| received.value);
+
+    Ok(value_from_zatoshis(
+        Zatoshis::from_u64(value).map_err(|_| RpcErrorCode::InternalError)?,
+    ))
+}
@@ -0,0 +1,161 @@
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned as RpcError};
+use serde::{Deserialize, Serialize};
+use tonic::transport::Channel;
+use zcash_client_backend::{
+    data_api::{scanning::ScanPriority, WalletRead},
+    proto::service::compact_tx_streamer_client::CompactTxStreamerClient,
+};
+
+use crate::components::{json_rpc::server::LegacyCode, wallet::WalletConnection};
+
+use super::get_block_count;
+
+/// Response to a `getsyncstatus` RPC request.
+pub(crate) type Response = RpcResult<SyncStatus>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct SyncStatus {
+    /// The height of the chain tip, as most recently observed by the wallet.
+    ///
+    /// Omitted if the wallet has not yet synced with the network.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) chain_tip_height: Option<u32>,
+
+    /// The height of the chain tip, as currently reported by `lightwalletd`.
+    ///
+    /// Omitted if Zallet is not connected to `lightwalletd`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) network_tip_height: Option<u32>,
+
+    /// The height below which every block relevant to the wallet has been scanned.
+    ///
+    /// Omitted if the wallet has not yet scanned any blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fully_scanned_height: Option<u32>,
+
+    /// The height of the highest block the wallet has scanned.
+    ///
+    /// Omitted if the wallet has not yet scanned any blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) max_scanned_height: Option<u32>,
+
+    /// The number of blocks between the wallet's birthday and the chain tip that have not
+    /// yet been scanned.
+    ///
+    /// This includes the blocks between the wallet's chain tip and the network's chain
+    /// tip, if the wallet has fallen behind.
+    unscanned_blocks: u32,
+
+    /// An estimate of how much of the chain between the wallet's birthday and the chain
+    /// tip has been scanned, as a percentage.
+    pub(super) progress: f64,
+
+    /// Whether the wallet is still scanning chain history, rather than only keeping up
+    /// with new blocks.
+    ///
+    /// Balances may be incomplete while this is `true`.
+    in_initial_recovery: bool,
+}
+
+pub(crate) async fn call(
+    wallet: &WalletConnection,
+    client: Option<CompactTxStreamerClient<Channel>>,
+) -> Response {
+    // If `lightwalletd` can't tell us the chain tip, we report what the wallet knows.
+    let network_tip_height = match client {
+        Some(mut client) => get_block_count::chain_tip_height(&mut client).await.ok(),
+        None => None,
+    };
+
+    let chain_tip_height = wallet.chain_height().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::chain_height failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    let fully_scanned_height = wallet
+        .block_fully_scanned()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::block_fully_scanned failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .map(|block| block.block_height());
+
+    let max_scanned_height = wallet
+        .block_max_scanned()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::block_max_scanned failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .map(|block| block.block_height());
+
+    let birthday = wallet.get_wallet_birthday().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::get_wallet_birthday failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    let scan_ranges = wallet.suggest_scan_ranges().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::suggest_scan_ranges failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    // Blocks the wallet hasn't learned about yet haven't been scanned either.
+    let chain_tip = chain_tip_height.map(u32::from);
+    let blocks_behind = match (chain_tip, network_tip_height) {
+        (Some(chain_tip), Some(network_tip)) => network_tip.saturating_sub(chain_tip),
+        _ => 0,
+    };
+    let unscanned_blocks = scan_ranges
+        .iter()
+        .map(|range| u32::from(range.block_range().end) - u32::from(range.block_range().start))
+        .sum::<u32>()
+        + blocks_behind;
+
+    let tip = chain_tip.map(|chain_tip| chain_tip + blocks_behind);
+    let progress = match (birthday.map(u32::from), tip) {
+        // A wallet with no accounts has nothing to scan.
+        (None, _) => 100.0,
+        (Some(birthday), Some(tip)) if tip >= birthday => {
+            let total = tip - birthday + 1;
+            let scanned = total.saturating_sub(unscanned_blocks);
+            100.0 * f64::from(scanned) / f64::from(total)
+        }
+        _ => 0.0,
+    };
+
+    // Once the wallet has scanned its history, the only ranges left to scan are the new
+    // blocks at the chain tip, and the blocks just below it that are re-verified after
+    // a restart. A wallet that has never seen the chain tip hasn't started at all.
+    let in_initial_recovery = birthday.is_some()
+        && (chain_tip_height.is_none()
+            || scan_ranges.iter().any(|range| {
+                !matches!(
+                    range.priority(),
+                    ScanPriority::ChainTip | ScanPriority::Verify
+                )
+            }));
+
+    Ok(SyncStatus {
+        chain_tip_height: chain_tip,
+        network_tip_height,
+        fully_scanned_height: fully_scanned_height.map(u32::from),
+        max_scanned_height: max_scanned_height.map(u32::from),
+        unscanned_blocks,
+        progress,
+        in_initial_recovery,
+    })
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use rusqlite::named_params;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zcash_client_backend::data_api::{Account as _, AccountPurpose, WalletRead};
+use zcash_client_sqlite::AccountUuid;
+use zcash_protocol::{consensus::BlockHeight, value::Zatoshis};
+
+use crate::components::{
+    json_rpc::{server::LegacyCode, value_to_string},
+    wallet::WalletConnection,
+};
+
+/// Response to a `z_gettotalbalance` RPC request.
+pub(crate) type Response = RpcResult<TotalBalance>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct TotalBalance {
+    /// The total value of unspent transparent outputs, in ZEC.
+    transparent: String,
+
+    /// The total value of unspent shielded notes, in ZEC.
+    private: String,
+
+    /// The total value of unspent transparent outputs and shielded notes, in ZEC.
+    total: String,
+}
+
+pub(crate) fn call(
+    wallet: &WalletConnection,
+    minconf: Option<u32>,
+    include_watchonly: Option<bool>,
+    as_of_height: Option<i32>,
+) -> Response {
+    let minconf = minconf.unwrap_or(1);
+
+    let chain_height = match wallet.chain_height().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::chain_height failed",
+            Some(format!("{e}")),
+        )
+    })? {
+        Some(height) => height,
+        // The wallet has not synced with the chain, so it cannot have a balance.
+        None => return Ok(TotalBalance::zero()),
+    };
+
+    let height = match as_of_height {
+        None | Some(-1) => chain_height,
+        Some(height) if height < 0 => {
+            return Err(RpcError::borrowed(
+                LegacyCode::InvalidParameter.into(),
+                "Can not perform the query as of a negative block height",
+                None,
+            ))
+        }
+        Some(height) => {
+            if minconf == 0 {
+                return Err(RpcError::borrowed(
+                    LegacyCode::InvalidParameter.into(),
+                    "Require a minimum of 1 confirmation when asOfHeight is provided",
+                    None,
+                ));
+            }
+
+            let height = BlockHeight::from_u32(height as u32);
+            if height > chain_height {
+                return Err(RpcError::borrowed(
+                    LegacyCode::InvalidParameter.into(),
+                    "asOfHeight is greater than the current chain tip",
+                    None,
+                ));
+            }
+            height
+        }
+    };
+
+    // We can't report a balance for blocks the wallet hasn't scanned yet.
+    let scanned_height = match wallet.block_fully_scanned().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::block_fully_scanned failed",
+            Some(format!("{e}")),
+        )
+    })? {
+        Some(block) => block.block_height(),
+        // The wallet hasn't scanned the chain from its birthday yet, so it can't know
+        // its balance at any height.
+        None => return Ok(TotalBalance::zero()),
+    };
+
+    let balances = balances_as_of(
+        wallet,
+        height.min(scanned_height),
+        minconf,
+        (height == chain_height).then_some(chain_height),
+    )?;
+
+    let mut transparent = 0;
+    let mut private = 0;
+    for (account_id, balance) in balances {
+        let account = wallet
+            .get_account(account_id)
+            .map_err(|e| {
+                RpcError::owned(
+                    LegacyCode::Database.into(),
+                    "WalletDb::get_account failed",
+                    Some(format!("{e}")),
+                )
+            })?
+            // This would be a race condition between this and account deletion.
+            .ok_or(RpcErrorCode::InternalError)?;
+
+        // If `includeWatchonly` is omitted, every account is included, as it was before
+        // the parameter was supported.
+        let spendable = matches!(account.purpose(), AccountPurpose::Spending { .. });
+        if include_watchonly == Some(false) && !spendable {
+            continue;
+        }
+
+        transparent += balance.transparent;
+        private += balance.private;
+    }
+
+    let to_value = |zat| Zatoshis::from_u64(zat).map_err(|_| RpcErrorCode::InternalError);
+
+    Ok(TotalBalance {
+        transparent: value_to_string(to_value(transparent)?),
+        private: value_to_string(to_value(private)?),
+        total: value_to_string(to_value(transparent + private)?),
+    })
+}
+
+/// An account's balance, in zatoshis.
+#[derive(Default)]
+struct AccountBalance {
+    transparent: u64,
+    private: u64,
+}
+
+/// Returns each account's balance as it was when the chain tip was at `as_of_height`,
+/// counting funds that had at least `minconf` confirmations at that height.
+///
+/// Funds are counted if they were received in a transaction mined with enough
+/// confirmations, and were not spent by a transaction mined at or below `as_of_height`.
+///
+/// If the balance is for the chain tip, `chain_tip` must be set, and unmined transactions
+/// that have not expired are also taken into account: funds that they spend are not
+/// counted, and if `minconf` is 0, funds that they receive are. Otherwise, they did not
+/// exist at `as_of_height`.
+fn balances_as_of(
+    wallet: &WalletConnection,
+    as_of_height: BlockHeight,
+    minconf: u32,
+    chain_tip: Option<BlockHeight>,
+) -> RpcResult<HashMap<AccountUuid, AccountBalance>> {
+    // The highest height at which received funds had `minconf` confirmations.
+    let max_mined_height = (u32::from(as_of_height) + 1).saturating_sub(minconf);
+
+    let unspent = |pool: &str, notes: &str, value: &str, tx: &str, spends: &str, note_id: &str| {
+        format!(
+            "SELECT '{pool}' AS pool, rn.account_id, rn.{value} AS value
+            FROM {notes} rn
+            JOIN transactions t ON t.id_tx = rn.{tx}
+            WHERE (
+                t.mined_height <= :max_mined_height
+                OR (:include_unmined_received AND {t_pending})
+            )
+            AND NOT EXISTS (
+                SELECT 1
+                FROM {spends} s
+                JOIN transactions stx ON stx.id_tx = s.transaction_id
+                WHERE s.{note_id} = rn.id
+                AND (
+                    stx.mined_height <= :as_of_height
+                    OR (:include_unmined_spends AND {stx_pending})
+                )
+            )",
+            t_pending = pending("t"),
+            stx_pending = pending("stx"),
+        )
+    };
+    let query = format!(
+        "SELECT accounts.uuid AS account_uuid, unspent.pool AS pool,
+                SUM(unspent.value) AS value
+        FROM ({} UNION ALL {} UNION ALL {}) unspent
+        JOIN accounts ON accounts.id = unspent.account_id
+        GROUP BY accounts.uuid, unspent.pool",
+        unspent(
+            "transparent",
+            "transparent_received_outputs",
+            "value_zat",
+            "transaction_id",
+            "transparent_received_output_spends",
+            "transparent_received_output_id",
+        ),
+        unspent(
+            "sapling",
+            "sapling_received_notes",
+            "value",
+            "tx",
+            "sapling_received_note_spends",
+            "sapling_received_note_id",
+        ),
+        unspent(
+            "orchard",
+            "orchard_received_notes",
+            "value",
+            "tx",
+            "orchard_received_note_spends",
+            "orchard_received_note_id",
+        ),
+    );
+
+    let rows = wallet
+        .with_raw(|conn| {
+            conn.prepare(&query)?
+                .query_map(
+                    named_params! {
+                        ":max_mined_height": max_mined_height,
+                        ":as_of_height": u32::from(as_of_height),
+                        ":chain_height": chain_tip.map(u32::from),
+                        ":include_unmined_received": chain_tip.is_some() && minconf == 0,
+                        ":include_unmined_spends": chain_tip.is_some(),
+                    },
+                    |row| {
+                        Ok((
+                            row.get::<_, Vec<u8>>("account_uuid")?,
+                            row.get::<_, String>("pool")?,
+                            row.get::<_, u64>("value")?,
+                        ))
+                    },
+                )?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query historical balances",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    let mut balances = HashMap::<_, AccountBalance>::new();
+    for (account_uuid, pool, value) in rows {
+        let account_id = Uuid::from_slice(&account_uuid)
+            .map(AccountUuid::from_uuid)
+            .map_err(|_| RpcErrorCode::InternalError)?;
+        let balance = balances.entry(account_id).or_default();
+        if pool == "transparent" {
+            balance.transparent += value;
+        } else {
+            balance.private += value;
+        }
+    }
+
+    Ok(balances)
+}
+
+/// Returns an SQL condition that is true if the transaction aliased as `tx` is unmined
+/// and has not expired as of `:chain_height`.
+fn pending(tx: &str) -> String {
+    format!(
+        "({tx}.mined_height IS NULL
+        AND (
+            {tx}.expiry_height IS NULL
+            OR {tx}.expiry_height = 0
+            OR {tx}.expiry_height > :chain_height
+        ))"
+    )
+}
+
+impl TotalBalance {
+    fn zero() -> Self {
+        Self {
+            transparent: value_to_string(Zatoshis::ZERO),
+            private: value_to_string(Zatoshis::ZERO),
+            total: value_to_string(Zatoshis::ZERO),
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use rusqlite::named_params;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zcash_client_backend::data_api::WalletRead;
+use zcash_protocol::{
+    consensus::BlockHeight,
+    value::{ZatBalance, Zatoshis},
+};
+
+use crate::components::{
+    json_rpc::{
+        parse_txid,
+        server::LegacyCode,
+        tx_status::{tx_status, wallet_conflicts, TxStatus},
+        value_from_zat_balance, value_from_zatoshis,
+    },
+    wallet::WalletConnection,
+};
+
+/// Response to a `gettransaction` RPC request.
+pub(crate) type Response = RpcResult<GetTransaction>;
+
+/// The number of confirmations that a coinbase transaction needs before its outputs can
+/// be spent.
+pub(super) const COINBASE_MATURITY: u32 = 100;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct GetTransaction {
+    /// The transaction ID.
+    txid: String,
+
+    /// The net change in the wallet's balance caused by this transaction, in ZEC.
+    amount: f64,
+
+    /// The fee paid by this transaction, in ZEC.
+    ///
+    /// This is negative, and is only present if the wallet spent funds in the
+    /// transaction and the fee is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fee: Option<f64>,
+
+    /// The transaction status.
+    ///
+    /// One of `["mined", "waiting", "expiringsoon", "expired", "conflicted"]`.
+    status: TxStatus,
+
+    /// The number of confirmations.
+    ///
+    /// - A positive value is the number of blocks that have been mined including the
+    ///   transaction in the chain.
+    /// - 0 means the transaction has not been mined.
+    /// - -1 means the transaction has not been mined, and a conflicting transaction
+    ///   has been mined.
+    confirmations: i64,
+
+    /// The hash of the block containing the transaction.
+    ///
+    /// Omitted if the transaction has not been mined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blockhash: Option<String>,
+
+    /// The index of the transaction within its block.
+    ///
+    /// Omitted if the transaction has not been mined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blockindex: Option<u32>,
+
+    /// The time in seconds since epoch (1 Jan 1970 GMT) of the block containing the
+    /// transaction.
+    ///
+    /// Omitted if the transaction has not been mined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blocktime: Option<i64>,
+
+    /// The block height after which the transaction expires.
+    ///
+    /// Omitted if the transaction never expires.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expiryheight: Option<u32>,
+
+    /// The IDs of other wallet transactions that spend any of the same notes or
+    /// transparent outputs as this transaction.
+    walletconflicts: Vec<String>,
+
+    /// The effects of the transaction on the wallet.
+    details: Vec<Detail>,
+
+    /// The raw transaction data.
+    ///
+    /// Omitted if the wallet has not yet retrieved the full transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hex: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Detail {
+    /// The UUID of the wallet account that sent or received the funds.
+    account_uuid: String,
+
+    /// The address that received the funds, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+
+    /// One of `["send", "receive", "generate", "immature", "orphan"]`.
+    ///
+    /// Funds received in a coinbase transaction are `"immature"` until they can be
+    /// spent, and `"orphan"` if the block that mined them is no longer in the main chain.
+    category: String,
+
+    /// The value pool containing the output.
+    ///
+    /// One of `["transparent", "sapling", "orchard"]`.
+    pool: String,
+
+    /// The amount in ZEC.
+    ///
+    /// Negative for the `send` category.
+    amount: f64,
+
+    /// The transparent output index, Sapling output index, or Orchard action index.
+    outindex: u32,
+}
+
+/// A row from the `v_transactions` view, which has one row per account involved in the
+/// transaction.
+struct AccountTx {
+    mined_height: Option<u32>,
+    tx_index: Option<u32>,
+    expiry_height: Option<u32>,
+    account_balance_delta: i64,
+    fee_paid: Option<u64>,
+    block_time: Option<i64>,
+    expired_unmined: bool,
+}
+
+/// A row from the `v_tx_outputs` view.
+struct TxOutput {
+    output_pool: i64,
+    output_index: u32,
+    from_account_uuid: Option<Vec<u8>>,
+    to_account_uuid: Option<Vec<u8>>,
+    to_address: Option<String>,
+    value: u64,
+    is_change: bool,
+}
+
+pub(crate) fn call(wallet: &WalletConnection, txid_str: &str) -> Response {
+    let txid = parse_txid(txid_str)?;
+
+    let account_txs = wallet
+        .with_raw(|conn| {
+            conn.prepare(
+                "SELECT mined_height, tx_index, expiry_height, account_balance_delta,
+                        fee_paid, block_time, expired_unmined
+                FROM v_transactions
+                WHERE txid = :txid",
+            )?
+            .query_map(named_params! {":txid": txid.as_ref()}, |row| {
+                Ok(AccountTx {
+                    mined_height: row.get("mined_height")?,
+                    tx_index: row.get("tx_index")?,
+                    expiry_height: row.get("expiry_height")?,
+                    account_balance_delta: row.get("account_balance_delta")?,
+                    fee_paid: row.get("fee_paid")?,
+                    block_time: row.get("block_time")?,
+                    expired_unmined: row.get("expired_unmined")?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query v_transactions",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    // All rows for a transaction share the same transaction-level fields.
+    let tx_info = account_txs.first().ok_or_else(|| {
+        RpcError::borrowed(
+            LegacyCode::InvalidAddressOrKey.into(),
+            "Invalid or non-wallet transaction id",
+            None,
+        )
+    })?;
+
+    let outputs = wallet
+        .with_raw(|conn| {
+            conn.prepare(
+                "SELECT output_pool, output_index, from_account_uuid, to_account_uuid,
+                        to_address, value, is_change
+                FROM v_tx_outputs
+                WHERE txid = :txid",
+            )?
+            .query_map(named_params! {":txid": txid.as_ref()}, |row| {
+                Ok(TxOutput {
+                    output_pool: row.get("output_pool")?,
+                    output_index: row.get("output_index")?,
+                    from_account_uuid: row.get("from_account_uuid")?,
+                    to_account_uuid: row.get("to_account_uuid")?,
+                    to_address: row.get("to_address")?,
+                    value: row.get("value")?,
+                    is_change: row.get("is_change")?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query v_tx_outputs",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    let conflicts = wallet_conflicts(wallet, txid)?;
+
+    let tx = wallet.get_transaction(txid).map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::get_transaction failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    let chain_height = wallet.chain_height().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::chain_height failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    let is_coinbase = tx
+        .as_ref()
+        .and_then(|tx| tx.transparent_bundle())
+        .map_or(false, |bundle| bundle.is_coinbase());
+
+    let hex = tx
+        .map(|tx| {
+            let mut data = vec![];
+            tx.write(&mut data)
+                .map(|()| hex::encode(data))
+                .map_err(|_| RpcErrorCode::InternalError)
+        })
+        .transpose()?;
+
+    let mined_height = tx_info.mined_height.map(BlockHeight::from_u32);
+
+    let status = tx_status(
+        wallet,
+        txid,
+        mined_height,
+        tx_info.expiry_height,
+        tx_info.expired_unmined,
+        chain_height,
+    )?;
+
+    let confirmations = match (mined_height, chain_height) {
+        (Some(mined_height), Some(chain_height)) => i64::from(chain_height + 1 - mined_height),
+        _ if status == TxStatus::Conflicted => -1,
+        _ => 0,
+    };
+
+    let blockhash = mined_height
+        .map(|height| wallet.get_block_hash(height))
+        .transpose()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::get_block_hash failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .flatten()
+        .map(|hash| hash.to_string());
+
+    let amount = account_txs
+        .iter()
+        .map(|account_tx| account_tx.account_balance_delta)
+        .sum::<i64>();
+    let amount = ZatBalance::from_i64(amount).map_err(|_| RpcErrorCode::InternalError)?;
+
+    let fee = account_txs
+        .iter()
+        .any(|account_tx| account_tx.account_balance_delta < 0)
+        .then_some(tx_info.fee_paid)
+        .flatten()
+        .map(|fee| {
+            Zatoshis::from_u64(fee)
+                .map(|fee| -value_from_zatoshis(fee))
+                .map_err(|_| RpcErrorCode::InternalError)
+        })
+        .transpose()?;
+
+    let mut details = vec![];
+    for output in outputs {
+        // Pool codes used by `zcash_client_sqlite`.
+        let pool = match output.output_pool {
+            0 => "transparent",
+            2 => "sapling",
+            3 => "orchard",
+            _ => return Err(RpcErrorCode::InternalError.into()),
+        };
+        let value = value_from_zatoshis(
+            Zatoshis::from_u64(output.value).map_err(|_| RpcErrorCode::InternalError)?,
+        );
+
+        // Change is an internal transfer, and is not reported (matching `zcashd`).
+        if output.is_change {
+            continue;
+        }
+
+        if let Some(from_account_uuid) = &output.from_account_uuid {
+            if output.to_account_uuid.as_ref() != Some(from_account_uuid) {
+                details.push(Detail {
+                    account_uuid: encode_uuid(from_account_uuid)?,
+                    address: output.to_address.clone(),
+                    category: "send".into(),
+                    pool: pool.into(),
+                    amount: -value,
+                    outindex: output.output_index,
+                });
+            }
+        }
+
+        if let Some(to_account_uuid) = &output.to_account_uuid {
+            details.push(Detail {
+                account_uuid: encode_uuid(to_account_uuid)?,
+                address: output.to_address,
+                category: receive_category(is_coinbase, mined_height, chain_height).into(),
+                pool: pool.into(),
+                amount: value,
+                outindex: output.output_index,
+            });
+        }
+    }
+
+    Ok(GetTransaction {
+        txid: txid.to_string(),
+        amount: value_from_zat_balance(amount),
+        fee,
+        status,
+        confirmations,
+        blockhash,
+        blockindex: tx_info.tx_index,
+        blocktime: tx_info.block_time,
+        expiryheight: tx_info.expiry_height.filter(|h| *h != 0),
+        walletconflicts: conflicts
+            .into_iter()
+            .map(|conflict| conflict.txid.to_string())
+            .collect(),
+        details,
+        hex,
+    })
+}
+
+/// Returns the category of funds received by the wallet in a transaction.
+///
+/// One of `["receive", "generate", "immature", "orphan"]`. Like `zcashd`, a coinbase
+/// transaction is only reported as `"generate"` once it has more than
+/// [`COINBASE_MATURITY`] confirmations, and is reported as `"orphan"` if it is unmined,
+/// because a coinbase transaction is only ever unmined by a reorg.
+pub(super) fn receive_category(
+    is_coinbase: bool,
+    mined_height: Option<BlockHeight>,
+    chain_height: Option<BlockHeight>,
+) -> &'static str {
+    match (mined_height, chain_height) {
+        _ if !is_coinbase => "receive",
+        (None, _) => "orphan",
+        (Some(mined_height), Some(chain_height))
+            if chain_height + 1 - mined_height > COINBASE_MATURITY =>
+        {
+            "generate"
+        }
+        _ => "immature",
+    }
+}
+
+pub(super) fn encode_uuid(bytes: &[u8]) -> RpcResult<String> {
+    Uuid::from_slice(bytes)
+        .map(|uuid| uuid.to_string())
+        .map_err(|_| RpcErrorCode::InternalError.into())
+}
@@ -1,15 +1,41 @@
-use jsonrpsee::{core::RpcResult, tracing::warn};
+use std::collections::BTreeSet;
+
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use rusqlite::named_params;
 use serde::{Deserialize, Serialize};
+use tonic::transport::Channel;
+use zcash_client_backend::{
+    data_api::{Account as _, WalletRead},
+    proto::service::compact_tx_streamer_client::CompactTxStreamerClient,
+};
+use zcash_protocol::value::Zatoshis;
+
+use crate::{
+    components::{
+        json_rpc::{server::LegacyCode, value_from_zatoshis, value_to_string},
+        wallet::{NotifyStatus, WalletConnection},
+    },
+    config::{BuilderSection, DeprecatedFeaturesSection},
+};
+
+use super::{get_sync_status, get_transaction::COINBASE_MATURITY};
 
 /// Response to a `getwalletinfo` RPC request.
 pub(crate) type Response = RpcResult<GetWalletInfo>;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub(crate) struct GetWalletInfo {
-    /// The wallet version, in its "Bitcoin client version" form.
-    walletversion: u64,
+    /// The wallet version.
+    ///
+    /// This is the number of database migrations that have been applied to the wallet.
+    walletversion: u32,
 
     /// The total confirmed transparent balance of the wallet in ZEC.
+    ///
+    /// This excludes immature coinbase outputs.
     balance: f64,
 
     /// The total unconfirmed transparent balance of the wallet in ZEC.
@@ -18,6 +44,8 @@ pub(crate) struct GetWalletInfo {
     unconfirmed_balance: Option<f64>,
 
     /// The total immature transparent balance of the wallet in ZEC.
+    ///
+    /// This is the value of unspent coinbase outputs with 100 or fewer confirmations.
     immature_balance: f64,
 
     /// The total confirmed shielded balance of the wallet in ZEC.
@@ -25,41 +53,383 @@ pub(crate) struct GetWalletInfo {
 
     /// The total unconfirmed shielded balance of the wallet in ZEC.
     ///
-    /// Not included if `asOfHeight` is specified.
+    /// This includes change and received notes that are not yet spendable because they
+    /// have too few confirmations. Not included if `asOfHeight` is specified.
     shielded_unconfirmed_balance: Option<String>,
 
     /// The total number of transactions in the wallet
     txcount: u64,
 
+    /// Fields that `zcashd` reported, with values adapted to Zallet.
+    ///
+    /// Only included if `features.deprecated.legacy_getwalletinfo_fields` is enabled.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    legacy: Option<LegacyFields>,
+
+    /// The timestamp in seconds since epoch (midnight Jan 1 1970 GMT) that the wallet is
+    /// unlocked for transfers, or 0 if the wallet is locked.
+    unlocked_until: u32,
+
+    /// The ZIP 32 fingerprint of the seed from which the wallet's accounts were derived,
+    /// in hex.
+    ///
+    /// Omitted if the wallet has no accounts derived from a seed, or has accounts derived
+    /// from more than one seed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mnemonic_seedfp: Option<String>,
+
+    /// The ZIP 317 fee parameters in force for transactions created by this wallet.
+    fee_parameters: FeeParameters,
+
+    /// How far the wallet has scanned the chain.
+    ///
+    /// Balances may be incomplete until `fraction` reaches 1.
+    scan_progress: ScanProgress,
+
+    /// The number of times in a row that the `notify` command has failed.
+    ///
+    /// This is reset to 0 when the command next succeeds.
+    notify_failures: u32,
+
+    /// The most recent error from the `notify` command.
+    ///
+    /// Omitted if the command has never failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notify_last_error: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct LegacyFields {
     /// The timestamp (seconds since GMT epoch) of the oldest pre-generated key in the
     /// key pool.
-    keypoololdest: u64,
+    ///
+    /// Zallet has no key pool, so this is always `null`.
+    keypoololdest: Option<u64>,
 
     /// How many new keys are pre-generated.
+    ///
+    /// Zallet has no key pool, so this is always 0.
     keypoolsize: u32,
 
-    /// The timestamp in seconds since epoch (midnight Jan 1 1970 GMT) that the wallet is
-    /// unlocked for transfers, or 0 if the wallet is locked.
-    unlocked_until: u32,
+    /// The transaction fee per kB configured with `settxfee`, in ZEC.
+    ///
+    /// Zallet always uses ZIP 317 fees (see `feerule`), so this is always 0.
+    paytxfee: f64,
 
-    /// The BLAKE2b-256 hash of the HD seed derived from the wallet's emergency recovery phrase.
-    mnemonic_seedfp: String,
+    /// The rule used to compute transaction fees. Always `"ZIP317"`.
+    feerule: String,
 }
 
-pub(crate) fn call() -> Response {
-    warn!("TODO: Implement getwalletinfo");
+impl LegacyFields {
+    /// Returns the legacy fields if they are enabled in the given config section.
+    fn new(deprecated: &DeprecatedFeaturesSection) -> Option<Self> {
+        deprecated
+            .legacy_getwalletinfo_fields()
+            .then(|| LegacyFields {
+                keypoololdest: None,
+                keypoolsize: 0,
+                paytxfee: 0.0,
+                feerule: "ZIP317".into(),
+            })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct FeeParameters {
+    /// The marginal fee per logical action, in zatoshis.
+    marginal_fee: u64,
+
+    /// The number of logical actions that every transaction pays for, even if it has
+    /// fewer.
+    grace_actions: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ScanProgress {
+    /// The height of the highest block the wallet has scanned.
+    ///
+    /// Omitted if the wallet has not yet scanned any blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scanned_height: Option<u32>,
+
+    /// The height of the chain tip, as most recently observed by the wallet.
+    ///
+    /// Omitted if the wallet has not yet synced with the network.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chain_tip_height: Option<u32>,
+
+    /// The fraction of the chain between the wallet's birthday and the chain tip that
+    /// has been scanned, between 0 and 1.
+    fraction: f64,
+}
+
+pub(crate) async fn call(
+    wallet: &WalletConnection,
+    client: Option<CompactTxStreamerClient<Channel>>,
+    notify_status: &NotifyStatus,
+    builder: &BuilderSection,
+    deprecated: &DeprecatedFeaturesSection,
+) -> Response {
+    let walletversion = wallet.schema_version().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "Failed to read the wallet schema version",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    let txcount = wallet
+        .with_raw(|conn| conn.query_row("SELECT COUNT(*) FROM transactions", [], |row| row.get(0)))
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to count wallet transactions",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    let chain_height = wallet
+        .chain_height()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::chain_height failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .map_or(0, u32::from);
+
+    let transparent = transparent_balances(wallet, chain_height)?;
+    let shielded = shielded_balances(wallet)?;
+    let mnemonic_seedfp = seed_fingerprint(wallet)?;
+
+    let sync_status = get_sync_status::call(wallet, client).await?;
 
     Ok(GetWalletInfo {
-        walletversion: 0,
-        balance: 0.0,
-        unconfirmed_balance: Some(0.0),
-        immature_balance: 0.0,
-        shielded_balance: "0.00".into(),
-        shielded_unconfirmed_balance: Some("0.00".into()),
-        txcount: 0,
-        keypoololdest: 0,
-        keypoolsize: 0,
+        walletversion,
+        balance: value(transparent.confirmed)?,
+        unconfirmed_balance: Some(value(transparent.unconfirmed)?),
+        immature_balance: value(transparent.immature)?,
+        shielded_balance: value_to_string(zatoshis(shielded.confirmed)?),
+        shielded_unconfirmed_balance: Some(value_to_string(zatoshis(shielded.unconfirmed)?)),
+        txcount,
+        legacy: LegacyFields::new(deprecated),
         unlocked_until: 0,
-        mnemonic_seedfp: "TODO".into(),
+        mnemonic_seedfp,
+        fee_parameters: FeeParameters {
+            marginal_fee: builder.fee_marginal_zatoshis(),
+            grace_actions: builder.fee_grace_actions(),
+        },
+        scan_progress: ScanProgress {
+            scanned_height: sync_status.max_scanned_height,
+            chain_tip_height: sync_status.chain_tip_height,
+            fraction: sync_status.progress / 100.0,
+        },
+        notify_failures: notify_status.consecutive_failures(),
+        notify_last_error: notify_status.last_error(),
+    })
+}
+
+/// The wallet's transparent balances, in zatoshis.
+struct TransparentBalances {
+    confirmed: u64,
+    unconfirmed: u64,
+    immature: u64,
+}
+
+/// Returns the value of the wallet's unspent transparent outputs.
+///
+/// Transactions that have expired without being mined are ignored, as are their spends.
+fn transparent_balances(
+    wallet: &WalletConnection,
+    chain_height: u32,
+) -> RpcResult<TransparentBalances> {
+    wallet
+        .with_raw(|conn| {
+            conn.query_row(
+                "WITH live_tx AS (
+                    SELECT id_tx FROM transactions
+                    WHERE mined_height IS NOT NULL
+                    OR expiry_height IS NULL
+                    OR expiry_height = 0
+                    OR expiry_height > :chain_height
+                )
+                SELECT
+                    COALESCE(SUM(CASE
+                        WHEN t.mined_height IS NOT NULL
+                        AND NOT (
+                            COALESCE(t.tx_index, -1) = 0
+                            AND t.mined_height > :immature_above
+                        )
+                        THEN o.value_zat
+                    END), 0) AS confirmed,
+                    COALESCE(SUM(CASE
+                        WHEN t.mined_height IS NULL THEN o.value_zat
+                    END), 0) AS unconfirmed,
+                    COALESCE(SUM(CASE
+                        WHEN COALESCE(t.tx_index, -1) = 0
+                        AND t.mined_height > :immature_above
+                        THEN o.value_zat
+                    END), 0) AS immature
+                FROM transparent_received_outputs o
+                JOIN transactions t ON t.id_tx = o.transaction_id
+                WHERE t.id_tx IN (SELECT id_tx FROM live_tx)
+                AND NOT EXISTS (
+                    SELECT 1 FROM transparent_received_output_spends s
+                    WHERE s.transparent_received_output_id = o.id
+                    AND s.transaction_id IN (SELECT id_tx FROM live_tx)
+                )",
+                named_params! {
+                    ":chain_height": chain_height,
+                    // Coinbase outputs mined above this height have 100 or fewer
+                    // confirmations.
+                    ":immature_above": chain_height.saturating_sub(COINBASE_MATURITY),
+                },
+                |row| {
+                    Ok(TransparentBalances {
+                        confirmed: row.get("confirmed")?,
+                        unconfirmed: row.get("unconfirmed")?,
+                        immature: row.get("immature")?,
+                    })
+                },
+            )
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query transparent balances",
+                Some(format!("{e}")),
+            )
+        })
+}
+
+/// The wallet's shielded balances, in zatoshis.
+struct ShieldedBalances {
+    confirmed: u64,
+    unconfirmed: u64,
+}
+
+/// Returns the wallet's spendable shielded balance, and the value of its shielded notes
+/// that are not yet spendable.
+fn shielded_balances(wallet: &WalletConnection) -> RpcResult<ShieldedBalances> {
+    let summary = wallet.get_wallet_summary(1).map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::get_wallet_summary failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    let mut balances = ShieldedBalances {
+        confirmed: 0,
+        unconfirmed: 0,
+    };
+    for balance in summary
+        .iter()
+        .flat_map(|summary| summary.account_balances().values())
+    {
+        for pool in [balance.sapling_balance(), balance.orchard_balance()] {
+            balances.confirmed += u64::from(pool.spendable_value());
+            balances.unconfirmed += u64::from(pool.change_pending_confirmation())
+                + u64::from(pool.value_pending_spendability());
+        }
+    }
+
+    Ok(balances)
+}
+
+/// Returns the fingerprint of the seed that the wallet's accounts were derived from, if
+/// there is exactly one such seed.
+fn seed_fingerprint(wallet: &WalletConnection) -> RpcResult<Option<String>> {
+    let db_error = |method, e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            format!("WalletDb::{method} failed"),
+            Some(format!("{e}")),
+        )
+    };
+
+    let mut seeds = BTreeSet::new();
+    for account_id in wallet
+        .get_account_ids()
+        .map_err(|e| db_error("get_account_ids", e))?
+    {
+        let account = wallet
+            .get_account(account_id)
+            .map_err(|e| db_error("get_account", e))?;
+        if let Some(derivation) = account.as_ref().and_then(|a| a.source().key_derivation()) {
+            seeds.insert(derivation.seed_fingerprint().to_bytes());
+        }
+    }
+
+    Ok(match seeds.len() {
+        1 => seeds.first().map(hex::encode),
+        _ => None,
     })
 }
+
+fn zatoshis(zat: u64) -> RpcResult<Zatoshis> {
+    Zatoshis::from_u64(zat).map_err(|_| RpcErrorCode::InternalError.into())
+}
+
+fn value(zat: u64) -> RpcResult<f64> {
+    zatoshis(zat).map(value_from_zatoshis)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::DeprecatedFeaturesSection;
+
+    use super::{FeeParameters, GetWalletInfo, LegacyFields, ScanProgress};
+
+    const LEGACY_FIELDS: &[&str] = &["keypoololdest", "keypoolsize", "paytxfee", "feerule"];
+
+    fn wallet_info(legacy_getwalletinfo_fields: Option<bool>) -> serde_json::Value {
+        let deprecated = DeprecatedFeaturesSection {
+            legacy_getwalletinfo_fields,
+        };
+        let info = GetWalletInfo {
+            walletversion: 1,
+            balance: 0.0,
+            unconfirmed_balance: Some(0.0),
+            immature_balance: 0.0,
+            shielded_balance: "0.00000000".into(),
+            shielded_unconfirmed_balance: Some("0.00000000".into()),
+            txcount: 0,
+            legacy: LegacyFields::new(&deprecated),
+            unlocked_until: 0,
+            mnemonic_seedfp: None,
+            fee_parameters: FeeParameters {
+                marginal_fee: 5000,
+                grace_actions: 2,
+            },
+            scan_progress: ScanProgress {
+                scanned_height: None,
+                chain_tip_height: None,
+                fraction: 0.0,
+            },
+            notify_failures: 0,
+            notify_last_error: None,
+        };
+        serde_json::to_value(info).unwrap()
+    }
+
+    #[test]
+    fn legacy_fields_omitted_by_default() {
+        for setting in [None, Some(false)] {
+            let info = wallet_info(setting);
+            for field in LEGACY_FIELDS {
+                assert!(info.get(field).is_none(), "{field} should be omitted");
+            }
+        }
+    }
+
+    #[test]
+    fn legacy_fields_included_when_enabled() {
+        let info = wallet_info(Some(true));
+        assert_eq!(info["keypoololdest"], serde_json::Value::Null);
+        assert_eq!(info["keypoolsize"], 0);
+        assert_eq!(info["paytxfee"], 0.0);
+        assert_eq!(info["feerule"], "ZIP317");
+    }
+}
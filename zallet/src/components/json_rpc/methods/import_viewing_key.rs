@@ -0,0 +1,220 @@
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned as RpcError};
+use serde::{Deserialize, Serialize};
+use tonic::transport::Channel;
+use zcash_client_backend::{
+    data_api::{Account as _, AccountBirthday, AccountPurpose, WalletRead, WalletWrite},
+    encoding::decode_extended_full_viewing_key,
+    keys::UnifiedFullViewingKey,
+    proto::service::{compact_tx_streamer_client::CompactTxStreamerClient, BlockId},
+};
+use zcash_protocol::consensus::{NetworkConstants, NetworkUpgrade, Parameters};
+
+use crate::components::{json_rpc::server::LegacyCode, wallet::WalletConnection};
+
+/// Response to a `z_importviewingkey` RPC request.
+pub(crate) type Response = RpcResult<ImportedViewingKey>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct ImportedViewingKey {
+    /// The UUID of the new view-only account.
+    account_uuid: String,
+
+    /// The height from which the wallet will scan the chain for the account's
+    /// transactions.
+    birthday_height: u32,
+
+    /// The blocks that have been queued for scanning to find the account's past
+    /// transactions.
+    ///
+    /// Omitted if `skip_rescan` was set, or if the wallet has not learned of any blocks
+    /// above the birthday height yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rescan: Option<RescanRange>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct RescanRange {
+    /// The first block height to be scanned.
+    start_height: u32,
+
+    /// The block height at which scanning stops (exclusive).
+    end_height: u32,
+}
+
+pub(crate) async fn call(
+    wallet: &mut WalletConnection,
+    mut client: CompactTxStreamerClient<Channel>,
+    vkey: &str,
+    start_height: Option<u32>,
+    skip_rescan: Option<bool>,
+) -> Response {
+    let ufvk = decode_viewing_key(wallet, vkey)?;
+
+    if wallet
+        .get_account_for_ufvk(&ufvk)
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::get_account_for_ufvk failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .is_some()
+    {
+        return Err(RpcError::borrowed(
+            LegacyCode::InvalidAddressOrKey.into(),
+            "The wallet already contains this viewing key",
+            None,
+        ));
+    }
+
+    // Unified viewing keys did not exist before Sapling activated, so by default we scan
+    // from there.
+    let start_height = start_height.unwrap_or_else(|| {
+        wallet
+            .params()
+            .activation_height(NetworkUpgrade::Sapling)
+            .map_or(1, u32::from)
+    });
+
+    if let Some(chain_height) = wallet.chain_height().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::chain_height failed",
+            Some(format!("{e}")),
+        )
+    })? {
+        if start_height > u32::from(chain_height) {
+            return Err(RpcError::borrowed(
+                LegacyCode::InvalidParameter.into(),
+                "Block height out of range",
+                None,
+            ));
+        }
+    }
+
+    // The birthday is defined by the note commitment trees as of the end of the block
+    // prior to the birthday height.
+    let treestate = client
+        .get_tree_state(BlockId {
+            height: u64::from(start_height.saturating_sub(1)),
+            hash: vec![],
+        })
+        .await
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Misc.into(),
+                "Failed to fetch tree state from lightwalletd",
+                Some(e.message().to_string()),
+            )
+        })?
+        .into_inner();
+
+    let birthday = AccountBirthday::from_treestate(treestate, None).map_err(|_| {
+        RpcError::borrowed(
+            LegacyCode::Misc.into(),
+            "lightwalletd returned an invalid tree state",
+            None,
+        )
+    })?;
+
+    // Importing the account queues a scan of the chain from its birthday, which the sync
+    // task will pick up.
+    let account = wallet
+        .import_account_ufvk("", &ufvk, &birthday, AccountPurpose::ViewOnly, None)
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::import_account_ufvk failed",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    // The account keeps its real birthday even if the rescan is skipped, so that the
+    // sync task still finds its past transactions; it just isn't woken to do so now.
+    let rescan = if skip_rescan.unwrap_or(false) {
+        None
+    } else {
+        queued_rescan(wallet, u32::from(birthday.height()))?
+    };
+
+    Ok(ImportedViewingKey {
+        account_uuid: account.id().expose_uuid().to_string(),
+        birthday_height: u32::from(birthday.height()),
+        rescan,
+    })
+}
+
+/// Returns the range of blocks from `birthday_height` onwards that are queued to be
+/// scanned, or `None` if there are none.
+fn queued_rescan(
+    wallet: &WalletConnection,
+    birthday_height: u32,
+) -> RpcResult<Option<RescanRange>> {
+    let ranges = wallet.suggest_scan_ranges().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::suggest_scan_ranges failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    Ok(ranges
+        .iter()
+        .map(|range| {
+            (
+                u32::from(range.block_range().start),
+                u32::from(range.block_range().end),
+            )
+        })
+        .filter(|(_, end)| *end > birthday_height)
+        .fold(None, |queued: Option<RescanRange>, (start, end)| {
+            let start = start.max(birthday_height);
+            Some(match queued {
+                None => RescanRange {
+                    start_height: start,
+                    end_height: end,
+                },
+                Some(queued) => RescanRange {
+                    start_height: queued.start_height.min(start),
+                    end_height: queued.end_height.max(end),
+                },
+            })
+        }))
+}
+
+/// Decodes a unified full viewing key, or a Sapling extended full viewing key (which is
+/// wrapped in a Sapling-only UFVK, as `zcashd` accepted these in `z_importviewingkey`).
+///
+/// This rejects keys for other networks.
+fn decode_viewing_key(wallet: &WalletConnection, vkey: &str) -> RpcResult<UnifiedFullViewingKey> {
+    let network = wallet.params().network_type();
+
+    if vkey.starts_with(network.hrp_sapling_extended_full_viewing_key()) {
+        let extfvk =
+            decode_extended_full_viewing_key(network.hrp_sapling_extended_full_viewing_key(), vkey)
+                .map_err(|e| {
+                    RpcError::owned(
+                        LegacyCode::InvalidAddressOrKey.into(),
+                        "Invalid Sapling extended full viewing key",
+                        Some(format!("{e}")),
+                    )
+                })?;
+
+        UnifiedFullViewingKey::from_sapling_extended_full_viewing_key(extfvk).map_err(|e| {
+            RpcError::owned(
+                LegacyCode::InvalidAddressOrKey.into(),
+                "Invalid Sapling extended full viewing key",
+                Some(format!("{e:?}")),
+            )
+        })
+    } else {
+        UnifiedFullViewingKey::decode(wallet.params(), vkey).map_err(|e| {
+            RpcError::owned(
+                LegacyCode::InvalidAddressOrKey.into(),
+                "Invalid unified full viewing key",
+                Some(e),
+            )
+        })
+    }
+}
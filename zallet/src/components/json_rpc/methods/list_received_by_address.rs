@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use rusqlite::named_params;
+use serde::{Deserialize, Serialize};
+use zcash_client_backend::{address::Address, data_api::WalletRead};
+use zcash_client_sqlite::AccountUuid;
+use zcash_protocol::{value::Zatoshis, TxId};
+
+use crate::components::{
+    json_rpc::{server::LegacyCode, value_from_zatoshis},
+    wallet::WalletConnection,
+};
+
+/// Response to a `listreceivedbyaddress` RPC request.
+pub(crate) type Response = RpcResult<Vec<ReceivedByAddress>>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct ReceivedByAddress {
+    /// The UUID of the wallet account that the address belongs to.
+    account_uuid: String,
+
+    /// The transparent address.
+    address: String,
+
+    /// The total amount received by the address, in ZEC.
+    amount: f64,
+
+    /// The number of confirmations of the most recent transaction included.
+    confirmations: u32,
+
+    /// The IDs of the transactions that paid the address.
+    txids: Vec<String>,
+}
+
+/// The outputs received by a single transparent address.
+#[derive(Default)]
+pub(super) struct Received {
+    pub(super) value: u64,
+    confirmations: u32,
+    txids: Vec<TxId>,
+}
+
+pub(crate) fn call(
+    wallet: &WalletConnection,
+    minconf: Option<u32>,
+    include_empty: Option<bool>,
+    address_filter: Option<&str>,
+) -> Response {
+    let minconf = minconf.unwrap_or(1);
+    let include_empty = include_empty.unwrap_or(false);
+
+    let addresses = wallet_transparent_addresses(wallet)?;
+
+    let address_filter = address_filter
+        .map(|address| parse_wallet_address(wallet, &addresses, address))
+        .transpose()?;
+
+    let mut received = received_by_address(wallet, minconf, address_filter.as_deref())?;
+
+    addresses
+        .into_iter()
+        .filter(|(address, _)| {
+            address_filter
+                .as_ref()
+                .map_or(true, |filter| filter == address)
+        })
+        .filter_map(|(address, account_uuid)| {
+            let received = received.remove(&address);
+            if received.is_none() && !include_empty {
+                return None;
+            }
+            let received = received.unwrap_or_default();
+
+            Some(
+                Zatoshis::from_u64(received.value)
+                    .map_err(|_| RpcErrorCode::InternalError.into())
+                    .map(|value| ReceivedByAddress {
+                        account_uuid: account_uuid.expose_uuid().to_string(),
+                        address,
+                        amount: value_from_zatoshis(value),
+                        confirmations: received.confirmations,
+                        txids: received.txids.iter().map(|txid| txid.to_string()).collect(),
+                    }),
+            )
+        })
+        .collect()
+}
+
+/// Returns the encodings of every transparent address in the wallet, along with the
+/// account each belongs to.
+pub(super) fn wallet_transparent_addresses(
+    wallet: &WalletConnection,
+) -> RpcResult<BTreeMap<String, AccountUuid>> {
+    let mut addresses = BTreeMap::new();
+
+    for account_id in wallet.get_account_ids().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::get_account_ids failed",
+            Some(format!("{e}")),
+        )
+    })? {
+        let receivers = wallet.get_transparent_receivers(account_id).map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::get_transparent_receivers failed",
+                Some(format!("{e}")),
+            )
+        })?;
+
+        addresses.extend(receivers.into_keys().map(|address| {
+            (
+                Address::Transparent(address).encode(wallet.params()),
+                account_id,
+            )
+        }));
+    }
+
+    Ok(addresses)
+}
+
+/// Parses a transparent address, and checks that it belongs to the wallet.
+///
+/// Returns the address in its canonical encoding.
+pub(super) fn parse_wallet_address(
+    wallet: &WalletConnection,
+    addresses: &BTreeMap<String, AccountUuid>,
+    address: &str,
+) -> RpcResult<String> {
+    // Addresses for other networks fail to decode.
+    let address = match Address::decode(wallet.params(), address) {
+        Some(address @ Address::Transparent(_)) => address.encode(wallet.params()),
+        Some(_) => {
+            return Err(RpcError::borrowed(
+                LegacyCode::InvalidAddressOrKey.into(),
+                "Only transparent addresses are supported",
+                None,
+            ))
+        }
+        None => {
+            return Err(RpcError::borrowed(
+                LegacyCode::InvalidAddressOrKey.into(),
+                "Invalid Zcash address",
+                None,
+            ))
+        }
+    };
+
+    if addresses.contains_key(&address) {
+        Ok(address)
+    } else {
+        Err(RpcError::borrowed(
+            LegacyCode::InvalidAddressOrKey.into(),
+            "Address not found in wallet",
+            None,
+        ))
+    }
+}
+
+/// Returns the outputs received by the wallet's transparent addresses in transactions
+/// with at least `minconf` confirmations.
+///
+/// If `minconf` is 0, unexpired transactions that have not been mined are included.
+pub(super) fn received_by_address(
+    wallet: &WalletConnection,
+    minconf: u32,
+    address: Option<&str>,
+) -> RpcResult<BTreeMap<String, Received>> {
+    let chain_height = wallet
+        .chain_height()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::chain_height failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .map(u32::from);
+
+    // If the wallet has not synced with the chain, nothing has been mined.
+    let max_height = (chain_height.unwrap_or(0) + 1).saturating_sub(minconf);
+
+    let rows = wallet
+        .with_raw(|conn| {
+            conn.prepare(
+                "SELECT tro.address, tro.value_zat, t.txid, t.mined_height
+                FROM transparent_received_outputs tro
+                JOIN transactions t ON t.id_tx = tro.transaction_id
+                WHERE (:address IS NULL OR tro.address = :address)
+                AND (
+                    t.mined_height <= :max_height
+                    OR (
+                        :minconf = 0
+                        AND t.mined_height IS NULL
+                        AND (
+                            t.expiry_height IS NULL
+                            OR t.expiry_height = 0
+                            OR t.expiry_height > :chain_height
+                        )
+                    )
+                )
+                ORDER BY
+                    t.mined_height IS NULL, t.mined_height, t.tx_index, t.id_tx,
+                    tro.output_index",
+            )?
+            .query_map(
+                named_params! {
+                    ":address": address,
+                    ":max_height": max_height,
+                    ":minconf": minconf,
+                    ":chain_height": chain_height.unwrap_or(0),
+                },
+                |row| {
+                    Ok((
+                        row.get::<_, String>("address")?,
+                        row.get::<_, u64>("value_zat")?,
+                        row.get::<_, Vec<u8>>("txid")?,
+                        row.get::<_, Option<u32>>("mined_height")?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query transparent_received_outputs",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    let mut received = BTreeMap::<_, Received>::new();
+    for (address, value, txid, mined_height) in rows {
+        let txid = TxId::from_bytes(txid.try_into().map_err(|_| RpcErrorCode::InternalError)?);
+        let confirmations = match (mined_height, chain_height) {
+            (Some(mined_height), Some(chain_height)) => {
+                (chain_height + 1).saturating_sub(mined_height)
+            }
+            _ => 0,
+        };
+
+        let entry = received.entry(address).or_default();
+        entry.value = entry
+            .value
+            .checked_add(value)
+            .ok_or(RpcErrorCode::InternalError)?;
+        // Rows are ordered by height, so this ends up as the most recent transaction.
+        entry.confirmations = confirmations;
+        if entry.txids.last() != Some(&txid) {
+            entry.txids.push(txid);
+        }
+    }
+
+    Ok(received)
+}
@@ -0,0 +1,177 @@
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned as RpcError};
+use rusqlite::{named_params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use zcash_client_backend::data_api::WalletRead;
+use zcash_primitives::block::BlockHash;
+use zcash_protocol::consensus::BlockHeight;
+
+use crate::components::{json_rpc::server::LegacyCode, wallet::WalletConnection};
+
+use super::list_transactions::{AccountTx, Transaction, ACCOUNT_TX_COLUMNS};
+
+/// Response to a `listsinceblock` RPC request.
+pub(crate) type Response = RpcResult<ListSinceBlock>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct ListSinceBlock {
+    /// The wallet transactions that were mined after the given block, or that have not
+    /// been mined.
+    transactions: Vec<Transaction>,
+
+    /// Wallet transactions that were in blocks that have been removed from the main chain
+    /// by a reorg since the given block.
+    ///
+    /// The wallet forgets blocks that are removed from the main chain, so a block that is
+    /// no longer in the main chain is not found, and this is always empty. It is included
+    /// for compatibility with Bitcoin Core.
+    removed: Vec<Transaction>,
+
+    /// The hash of the block `target_confirmations - 1` blocks below the chain tip (or
+    /// the wallet's fully-scanned height, if lower), to pass to the next call.
+    ///
+    /// Omitted if the wallet has not yet scanned any blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lastblock: Option<String>,
+}
+
+pub(crate) fn call(
+    wallet: &WalletConnection,
+    blockhash: Option<&str>,
+    target_confirmations: Option<u32>,
+) -> Response {
+    let target_confirmations = target_confirmations.unwrap_or(1);
+    if target_confirmations < 1 {
+        return Err(RpcError::borrowed(
+            LegacyCode::InvalidParameter.into(),
+            "Invalid parameter",
+            None,
+        ));
+    }
+
+    let block_hash = blockhash.map(parse_block_hash).transpose()?;
+
+    let chain_height = wallet.chain_height().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::chain_height failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    // Find the height of the given block. Like Bitcoin Core, we reject blocks that we
+    // don't know about.
+    let since_height = match block_hash {
+        None => 0,
+        Some(hash) => wallet
+            .with_raw(|conn| {
+                conn.query_row(
+                    "SELECT height FROM blocks WHERE hash = :hash",
+                    named_params! {":hash": &hash.0[..]},
+                    |row| row.get::<_, u32>("height"),
+                )
+                .optional()
+            })
+            .map_err(|e| {
+                RpcError::owned(
+                    LegacyCode::Database.into(),
+                    "Failed to query blocks",
+                    Some(format!("{e}")),
+                )
+            })?
+            .ok_or_else(|| {
+                RpcError::borrowed(
+                    LegacyCode::InvalidAddressOrKey.into(),
+                    "Block not found",
+                    None,
+                )
+            })?,
+    };
+
+    let account_txs = wallet
+        .with_raw(|conn| {
+            conn.prepare(&format!(
+                "SELECT {ACCOUNT_TX_COLUMNS}
+                FROM v_transactions v
+                LEFT JOIN blocks ON blocks.height = v.mined_height
+                WHERE v.mined_height > :since_height
+                OR (
+                    v.mined_height IS NULL
+                    AND (
+                        v.expiry_height IS NULL
+                        OR v.expiry_height = 0
+                        OR v.expiry_height > :since_height
+                    )
+                )
+                ORDER BY v.mined_height IS NULL, v.mined_height, v.tx_index"
+            ))?
+            .query_map(
+                named_params! {":since_height": since_height},
+                AccountTx::from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query v_transactions",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    let transactions = account_txs
+        .into_iter()
+        .map(|account_tx| Transaction::from_account_tx(wallet, account_tx, chain_height))
+        .collect::<RpcResult<Vec<_>>>()?;
+
+    // Only report blocks that the wallet has fully scanned, so that the next call
+    // doesn't miss transactions in blocks that are still being scanned.
+    let fully_scanned_height = wallet
+        .block_fully_scanned()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::block_fully_scanned failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .map(|block| block.block_height());
+
+    let lastblock = match (chain_height, fully_scanned_height) {
+        (Some(chain_height), Some(fully_scanned_height)) => {
+            let target_height = BlockHeight::from_u32(
+                (u32::from(chain_height) + 1).saturating_sub(target_confirmations),
+            );
+            wallet
+                .get_block_hash(target_height.min(fully_scanned_height))
+                .map_err(|e| {
+                    RpcError::owned(
+                        LegacyCode::Database.into(),
+                        "WalletDb::get_block_hash failed",
+                        Some(format!("{e}")),
+                    )
+                })?
+                .map(|hash| hash.to_string())
+        }
+        _ => None,
+    };
+
+    Ok(ListSinceBlock {
+        transactions,
+        removed: vec![],
+        lastblock,
+    })
+}
+
+/// Parses a block hash from its RPC encoding (byte-reversed hex).
+fn parse_block_hash(hash_str: &str) -> RpcResult<BlockHash> {
+    let mut bytes = [0; 32];
+    hex::decode_to_slice(hash_str, &mut bytes).map_err(|_| {
+        RpcError::borrowed(
+            LegacyCode::InvalidParameter.into(),
+            "invalid block hash",
+            None,
+        )
+    })?;
+    bytes.reverse();
+    Ok(BlockHash(bytes))
+}
@@ -0,0 +1,288 @@
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use rusqlite::named_params;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zcash_client_backend::data_api::WalletRead;
+use zcash_primitives::block::BlockHash;
+use zcash_protocol::{
+    consensus::BlockHeight,
+    value::{ZatBalance, Zatoshis},
+    TxId,
+};
+
+use crate::components::{
+    json_rpc::{
+        server::LegacyCode,
+        tx_status::{tx_status, TxStatus},
+        value_from_zat_balance, value_from_zatoshis,
+    },
+    wallet::WalletConnection,
+};
+
+use super::get_transaction::{encode_uuid, receive_category};
+
+/// Response to a `listtransactions` RPC request.
+pub(crate) type Response = RpcResult<Vec<Transaction>>;
+
+/// The columns of `v_transactions` (aliased as `v`, and left-joined with `blocks`) that
+/// are read by [`AccountTx::from_row`].
+pub(super) const ACCOUNT_TX_COLUMNS: &str =
+    "v.account_uuid, v.txid, v.mined_height, v.tx_index, v.expiry_height,
+    v.account_balance_delta, v.fee_paid, v.block_time, v.expired_unmined,
+    blocks.hash AS block_hash";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct Transaction {
+    /// The UUID of the wallet account involved in the transaction.
+    account_uuid: String,
+
+    /// The transaction ID.
+    txid: String,
+
+    /// One of `["send", "receive", "generate", "immature", "orphan"]`.
+    ///
+    /// Funds received in a coinbase transaction are `"immature"` until they can be
+    /// spent, and `"orphan"` if the block that mined them is no longer in the main chain.
+    category: String,
+
+    /// The net change in the account's balance caused by this transaction, in ZEC.
+    amount: f64,
+
+    /// The fee paid by this transaction, in ZEC.
+    ///
+    /// This is negative, and is only present for the `send` category if the fee is
+    /// known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fee: Option<f64>,
+
+    /// The transaction status.
+    ///
+    /// One of `["mined", "waiting", "expiringsoon", "expired", "conflicted"]`.
+    status: TxStatus,
+
+    /// The number of confirmations.
+    confirmations: u32,
+
+    /// The hash of the block containing the transaction.
+    ///
+    /// Omitted if the transaction has not been mined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blockhash: Option<String>,
+
+    /// The index of the transaction within its block.
+    ///
+    /// Omitted if the transaction has not been mined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blockindex: Option<u32>,
+
+    /// The time in seconds since epoch (1 Jan 1970 GMT) of the block containing the
+    /// transaction.
+    ///
+    /// Omitted if the transaction has not been mined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blocktime: Option<i64>,
+
+    /// The transaction time in seconds since epoch (1 Jan 1970 GMT).
+    ///
+    /// This is currently the same as `blocktime`, and is omitted if the transaction has
+    /// not been mined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<i64>,
+}
+
+/// A row from the `v_transactions` view, which has one row per account involved in each
+/// transaction.
+pub(super) struct AccountTx {
+    account_uuid: Vec<u8>,
+    txid: Vec<u8>,
+    mined_height: Option<u32>,
+    tx_index: Option<u32>,
+    expiry_height: Option<u32>,
+    account_balance_delta: i64,
+    fee_paid: Option<u64>,
+    block_time: Option<i64>,
+    expired_unmined: bool,
+    block_hash: Option<Vec<u8>>,
+}
+
+pub(crate) fn call(
+    wallet: &WalletConnection,
+    account: Option<&str>,
+    count: Option<u32>,
+    from: Option<u32>,
+) -> Response {
+    // `"*"` (as in Bitcoin Core) lists transactions across every account.
+    let account_uuid = match account {
+        None | Some("*") => None,
+        Some(account) => Some(Uuid::parse_str(account).map_err(|_| {
+            RpcError::borrowed(
+                LegacyCode::InvalidParameter.into(),
+                "account must be an account UUID or \"*\"",
+                None,
+            )
+        })?),
+    };
+    let count = count.unwrap_or(10);
+    let from = from.unwrap_or(0);
+
+    let chain_height = wallet.chain_height().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::chain_height failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    // Select the most recent transactions (unmined transactions are the most recent),
+    // skipping the first `from`.
+    let mut account_txs = wallet
+        .with_raw(|conn| {
+            conn.prepare(&format!(
+                "SELECT {ACCOUNT_TX_COLUMNS}
+                FROM v_transactions v
+                LEFT JOIN blocks ON blocks.height = v.mined_height
+                WHERE :account_uuid IS NULL OR v.account_uuid = :account_uuid
+                ORDER BY v.mined_height IS NULL DESC, v.mined_height DESC, v.tx_index DESC
+                LIMIT :count OFFSET :from"
+            ))?
+            .query_map(
+                named_params! {
+                    ":account_uuid": account_uuid.as_ref().map(|uuid| &uuid.as_bytes()[..]),
+                    ":count": count,
+                    ":from": from,
+                },
+                AccountTx::from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query v_transactions",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    // Like Bitcoin Core, return the oldest transaction first.
+    account_txs.reverse();
+
+    account_txs
+        .into_iter()
+        .map(|account_tx| Transaction::from_account_tx(wallet, account_tx, chain_height))
+        .collect()
+}
+
+impl AccountTx {
+    /// Reads the [`ACCOUNT_TX_COLUMNS`] from a row.
+    pub(super) fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            account_uuid: row.get("account_uuid")?,
+            txid: row.get("txid")?,
+            mined_height: row.get("mined_height")?,
+            tx_index: row.get("tx_index")?,
+            expiry_height: row.get("expiry_height")?,
+            account_balance_delta: row.get("account_balance_delta")?,
+            fee_paid: row.get("fee_paid")?,
+            block_time: row.get("block_time")?,
+            expired_unmined: row.get("expired_unmined")?,
+            block_hash: row.get("block_hash")?,
+        })
+    }
+
+    /// Returns `true` if this is a coinbase transaction.
+    ///
+    /// Only a coinbase transaction can be first in its block, so the transaction itself
+    /// is only read if it isn't in a block.
+    fn is_coinbase(&self, wallet: &WalletConnection, txid: TxId) -> RpcResult<bool> {
+        match self.tx_index {
+            Some(tx_index) => Ok(tx_index == 0),
+            None => Ok(wallet
+                .get_transaction(txid)
+                .map_err(|e| {
+                    RpcError::owned(
+                        LegacyCode::Database.into(),
+                        "WalletDb::get_transaction failed",
+                        Some(format!("{e}")),
+                    )
+                })?
+                .and_then(|tx| tx.transparent_bundle().map(|bundle| bundle.is_coinbase()))
+                .unwrap_or(false)),
+        }
+    }
+}
+
+impl Transaction {
+    pub(super) fn from_account_tx(
+        wallet: &WalletConnection,
+        row: AccountTx,
+        chain_height: Option<BlockHeight>,
+    ) -> RpcResult<Self> {
+        let txid = TxId::from_bytes(
+            row.txid
+                .try_into()
+                .map_err(|_| RpcErrorCode::InternalError)?,
+        );
+        let mined_height = row.mined_height.map(BlockHeight::from_u32);
+
+        let confirmations = match (mined_height, chain_height) {
+            (Some(mined_height), Some(chain_height)) => chain_height + 1 - mined_height,
+            _ => 0,
+        };
+
+        let amount = ZatBalance::from_i64(row.account_balance_delta)
+            .map_err(|_| RpcErrorCode::InternalError)?;
+        let is_send = row.account_balance_delta < 0;
+
+        // A coinbase transaction has no inputs, so the wallet can't have sent it.
+        let category = if is_send {
+            "send"
+        } else {
+            receive_category(row.is_coinbase(wallet, txid)?, mined_height, chain_height)
+        };
+
+        let fee = row
+            .fee_paid
+            .filter(|_| is_send)
+            .map(|fee| {
+                Zatoshis::from_u64(fee)
+                    .map(|fee| -value_from_zatoshis(fee))
+                    .map_err(|_| RpcErrorCode::InternalError)
+            })
+            .transpose()?;
+
+        let status = tx_status(
+            wallet,
+            txid,
+            mined_height,
+            row.expiry_height,
+            row.expired_unmined,
+            chain_height,
+        )?;
+
+        let blockhash = row
+            .block_hash
+            .map(|hash| {
+                BlockHash::try_from_slice(&hash)
+                    .map(|hash| hash.to_string())
+                    .ok_or(RpcErrorCode::InternalError)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            account_uuid: encode_uuid(&row.account_uuid)?,
+            txid: txid.to_string(),
+            category: category.into(),
+            amount: value_from_zat_balance(amount),
+            fee,
+            status,
+            confirmations,
+            blockhash: blockhash.filter(|_| mined_height.is_some()),
+            blockindex: row.tx_index,
+            blocktime: row.block_time,
+            time: row.block_time,
+        })
+    }
+}
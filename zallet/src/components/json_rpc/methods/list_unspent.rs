@@ -5,13 +5,15 @@ use jsonrpsee::{
     types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
 };
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 use zcash_client_backend::{
-    address::UnifiedAddress,
+    address::{Address, UnifiedAddress},
     data_api::{Account, AccountPurpose, InputSource, NullifierQuery, WalletRead},
     encoding::AddressCodec,
     fees::{orchard::InputView as _, sapling::InputView as _},
     wallet::NoteId,
 };
+use zcash_client_sqlite::AccountUuid;
 use zcash_protocol::{
     value::{Zatoshis, MAX_MONEY},
     ShieldedProtocol,
@@ -73,7 +75,86 @@ pub(crate) struct UnspentNote {
     change: Option<bool>,
 }
 
-pub(crate) fn call(wallet: &WalletConnection) -> Response {
+/// The shielded receivers that notes must have been sent to, and the accounts that own
+/// them.
+struct AddressFilter {
+    accounts: Vec<AccountUuid>,
+    sapling: Vec<sapling::PaymentAddress>,
+    orchard: Vec<orchard::Address>,
+}
+
+pub(crate) fn call(
+    wallet: &WalletConnection,
+    minconf: Option<u32>,
+    maxconf: Option<u32>,
+    include_watchonly: Option<bool>,
+    addresses: Option<Vec<String>>,
+    account: Option<&str>,
+) -> Response {
+    let minconf = minconf.unwrap_or(1);
+    let maxconf = maxconf.unwrap_or(9_999_999);
+
+    if maxconf < minconf {
+        return Err(RpcError::borrowed(
+            LegacyCode::InvalidParameter.into(),
+            "Maximum number of confirmations must be greater or equal to the minimum number of confirmations",
+            None,
+        ));
+    }
+
+    let account_filter = account
+        .map(|account| {
+            Uuid::parse_str(account)
+                .map(AccountUuid::from_uuid)
+                .map_err(|_| {
+                    RpcError::borrowed(
+                        LegacyCode::InvalidParameter.into(),
+                        "account must be an account UUID",
+                        None,
+                    )
+                })
+        })
+        .transpose()?;
+
+    let accounts = wallet
+        .get_account_ids()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::get_account_ids failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .into_iter()
+        .map(|account_id| {
+            wallet
+                .get_account(account_id)
+                .map_err(|e| {
+                    RpcError::owned(
+                        LegacyCode::Database.into(),
+                        "WalletDb::get_account failed",
+                        Some(format!("{e}")),
+                    )
+                })?
+                // This would be a race condition between this and account deletion.
+                .ok_or_else(|| RpcErrorCode::InternalError.into())
+        })
+        .collect::<RpcResult<Vec<_>>>()?;
+
+    if let Some(account_id) = account_filter {
+        if !accounts.iter().any(|account| account.id() == account_id) {
+            return Err(RpcError::borrowed(
+                LegacyCode::InvalidParameter.into(),
+                "Unknown account",
+                None,
+            ));
+        }
+    }
+
+    let address_filter = addresses
+        .map(|addresses| resolve_addresses(wallet, &accounts, &addresses))
+        .transpose()?;
+
     // Use the height of the maximum scanned block as the anchor height, to emulate a
     // zero-conf transaction in order to select every note in the wallet.
     let anchor_height = match wallet.block_max_scanned().map_err(|e| {
@@ -89,26 +170,23 @@ pub(crate) fn call(wallet: &WalletConnection) -> Response {
 
     let mut unspent_notes = vec![];
 
-    for account_id in wallet.get_account_ids().map_err(|e| {
-        RpcError::owned(
-            LegacyCode::Database.into(),
-            "WalletDb::get_account_ids failed",
-            Some(format!("{e}")),
-        )
-    })? {
-        let account = wallet
-            .get_account(account_id)
-            .map_err(|e| {
-                RpcError::owned(
-                    LegacyCode::Database.into(),
-                    "WalletDb::get_account failed",
-                    Some(format!("{e}")),
-                )
-            })?
-            // This would be a race condition between this and account deletion.
-            .ok_or_else(|| RpcErrorCode::InternalError)?;
+    for account in accounts {
+        let account_id = account.id();
+        if account_filter.is_some_and(|filter| filter != account_id)
+            || address_filter
+                .as_ref()
+                .is_some_and(|filter| !filter.accounts.contains(&account_id))
+        {
+            continue;
+        }
 
+        // If `includeWatchonly` is omitted, notes in every account are listed, so that
+        // the notes in view-only accounts (which are the only accounts that Zallet can
+        // create for now) are not hidden by default.
         let spendable = matches!(account.purpose(), AccountPurpose::Spending { .. });
+        if include_watchonly == Some(false) && !spendable {
+            continue;
+        }
 
         // `z_listunspent` assumes a single HD seed.
         // TODO: Fix this limitation.
@@ -180,6 +258,14 @@ pub(crate) fn call(wallet: &WalletConnection) -> Response {
                 })?
                 .map(|h| anchor_height + 1 - h)
                 .unwrap_or(0);
+            if confirmations < minconf
+                || confirmations > maxconf
+                || address_filter
+                    .as_ref()
+                    .is_some_and(|filter| !filter.sapling.contains(&note.note().recipient()))
+            {
+                continue;
+            }
 
             let is_internal = note.spending_key_scope() == Scope::Internal;
 
@@ -248,6 +334,14 @@ pub(crate) fn call(wallet: &WalletConnection) -> Response {
                 })?
                 .map(|h| anchor_height + 1 - h)
                 .unwrap_or(0);
+            if confirmations < minconf
+                || confirmations > maxconf
+                || address_filter
+                    .as_ref()
+                    .is_some_and(|filter| !filter.orchard.contains(&note.note().recipient()))
+            {
+                continue;
+            }
 
             let is_internal = note.spending_key_scope() == Scope::Internal;
 
@@ -277,3 +371,76 @@ pub(crate) fn call(wallet: &WalletConnection) -> Response {
 
     Ok(unspent_notes)
 }
+
+/// Resolves the given addresses to the shielded receivers they contain, checking that
+/// each receiver belongs to one of the wallet's accounts.
+fn resolve_addresses(
+    wallet: &WalletConnection,
+    accounts: &[zcash_client_sqlite::wallet::Account],
+    addresses: &[String],
+) -> RpcResult<AddressFilter> {
+    let mut filter = AddressFilter {
+        accounts: vec![],
+        sapling: vec![],
+        orchard: vec![],
+    };
+
+    let owner = |is_mine: &dyn Fn(&zcash_client_backend::keys::UnifiedFullViewingKey) -> bool| {
+        accounts
+            .iter()
+            .find(|account| account.ufvk().is_some_and(is_mine))
+            .map(|account| account.id())
+            .ok_or_else(|| {
+                RpcError::borrowed(
+                    LegacyCode::InvalidAddressOrKey.into(),
+                    "Address does not belong to the wallet",
+                    None,
+                )
+            })
+    };
+
+    for address in addresses {
+        let (sapling, orchard) = match Address::decode(wallet.params(), address) {
+            Some(Address::Sapling(addr)) => (Some(addr), None),
+            Some(Address::Unified(ua)) => (ua.sapling().copied(), ua.orchard().copied()),
+            Some(_) => {
+                return Err(RpcError::owned(
+                    LegacyCode::InvalidAddressOrKey.into(),
+                    format!("Invalid parameter, address is not a shielded address: {address}"),
+                    None::<()>,
+                ))
+            }
+            None => {
+                return Err(RpcError::owned(
+                    LegacyCode::InvalidAddressOrKey.into(),
+                    format!("Invalid parameter, unknown address format: {address}"),
+                    None::<()>,
+                ))
+            }
+        };
+
+        if let Some(addr) = sapling {
+            filter.accounts.push(owner(&|ufvk| {
+                ufvk.sapling()
+                    .is_some_and(|dfvk| dfvk.decrypt_diversifier(&addr).is_some())
+            })?);
+            filter.sapling.push(addr);
+        }
+        if let Some(addr) = orchard {
+            filter.accounts.push(owner(&|ufvk| {
+                ufvk.orchard()
+                    .is_some_and(|fvk| fvk.scope_for_address(&addr).is_some())
+            })?);
+            filter.orchard.push(addr);
+        }
+        if sapling.is_none() && orchard.is_none() {
+            return Err(RpcError::owned(
+                LegacyCode::InvalidAddressOrKey.into(),
+                format!("Invalid parameter, address is not a shielded address: {address}"),
+                None::<()>,
+            ));
+        }
+    }
+
+    Ok(filter)
+}
@@ -0,0 +1,94 @@
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned as RpcError};
+use serde::{Deserialize, Serialize};
+use zcash_client_backend::data_api::WalletRead;
+use zcash_protocol::consensus::BlockHeight;
+
+use crate::components::{
+    json_rpc::server::LegacyCode,
+    wallet::{queue_rescan, WalletConnection},
+};
+
+/// Response to a `rescanblockchain` RPC request.
+pub(crate) type Response = RpcResult<RescanBlockchain>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct RescanBlockchain {
+    /// The height from which the wallet will rescan the chain.
+    start_height: u32,
+
+    /// The height of the chain tip at the time of the request, up to which the wallet
+    /// will rescan.
+    stop_height: u32,
+}
+
+pub(crate) fn call(wallet: &mut WalletConnection, start_height: Option<u32>) -> Response {
+    let birthday = wallet
+        .get_wallet_birthday()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::get_wallet_birthday failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .ok_or_else(|| {
+            RpcError::borrowed(
+                LegacyCode::InvalidParameter.into(),
+                "The wallet has no accounts to rescan",
+                None,
+            )
+        })?;
+
+    let chain_tip = wallet
+        .chain_height()
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "WalletDb::chain_height failed",
+                Some(format!("{e}")),
+            )
+        })?
+        .ok_or_else(|| {
+            RpcError::borrowed(
+                LegacyCode::InWarmup.into(),
+                "The wallet has not yet synced with the chain",
+                None,
+            )
+        })?;
+
+    let start_height = start_height.map_or(birthday, BlockHeight::from_u32);
+    if start_height < birthday {
+        return Err(RpcError::owned(
+            LegacyCode::InvalidParameter.into(),
+            format!("start_height must not be below the wallet birthday ({birthday})"),
+            None::<()>,
+        ));
+    }
+    if start_height > chain_tip {
+        return Err(RpcError::borrowed(
+            LegacyCode::InvalidParameter.into(),
+            "Block height out of range",
+            None,
+        ));
+    }
+
+    // The sync task scans the queued blocks again on its next pass.
+    wallet
+        .with_raw_mut(|conn| {
+            let tx = conn.transaction()?;
+            queue_rescan(&tx, start_height)?;
+            tx.commit()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to queue the rescan",
+                Some(format!("{e}")),
+            )
+        })?;
+
+    Ok(RescanBlockchain {
+        start_height: u32::from(start_height),
+        stop_height: u32::from(chain_tip),
+    })
+}
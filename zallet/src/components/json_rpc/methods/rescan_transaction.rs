@@ -0,0 +1,209 @@
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorCode as RpcErrorCode, ErrorObjectOwned as RpcError},
+};
+use rusqlite::named_params;
+use serde::{Deserialize, Serialize};
+use tonic::transport::Channel;
+use zcash_client_backend::{
+    data_api::{wallet::decrypt_and_store_transaction, WalletRead},
+    proto::service::{compact_tx_streamer_client::CompactTxStreamerClient, TxFilter},
+};
+use zcash_primitives::transaction::Transaction;
+use zcash_protocol::{
+    consensus::{BlockHeight, BranchId},
+    value::Zatoshis,
+    TxId,
+};
+
+use crate::components::{
+    json_rpc::{parse_txid, server::LegacyCode, value_from_zatoshis},
+    wallet::WalletConnection,
+};
+
+use super::get_transaction::encode_uuid;
+
+/// Response to a `rescantransaction` RPC request.
+pub(crate) type Response = RpcResult<RescanTransaction>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct RescanTransaction {
+    /// The transaction ID.
+    txid: String,
+
+    /// The height of the block containing the transaction.
+    ///
+    /// Omitted if the transaction has not been mined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+
+    /// The outputs involving the wallet that were discovered by this rescan.
+    discovered: Vec<Output>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Output {
+    /// The value pool containing the output.
+    ///
+    /// One of `["transparent", "sapling", "orchard"]`.
+    pool: String,
+
+    /// The transparent output index, Sapling output index, or Orchard action index.
+    outindex: u32,
+
+    /// The UUID of the wallet account that sent the output, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from_account_uuid: Option<String>,
+
+    /// The UUID of the wallet account that received the output, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to_account_uuid: Option<String>,
+
+    /// The amount in ZEC.
+    amount: f64,
+}
+
+/// A row from the `v_tx_outputs` view.
+#[derive(PartialEq)]
+struct TxOutput {
+    output_pool: i64,
+    output_index: u32,
+    from_account_uuid: Option<Vec<u8>>,
+    to_account_uuid: Option<Vec<u8>>,
+    value: u64,
+}
+
+pub(crate) async fn call(
+    wallet: &mut WalletConnection,
+    mut client: CompactTxStreamerClient<Channel>,
+    txid_str: &str,
+) -> Response {
+    let txid = parse_txid(txid_str)?;
+
+    let raw_tx = client
+        .get_transaction(TxFilter {
+            block: None,
+            index: 0,
+            hash: txid.as_ref().to_vec(),
+        })
+        .await
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::InvalidAddressOrKey.into(),
+                "Failed to fetch transaction from lightwalletd",
+                Some(e.message().to_string()),
+            )
+        })?
+        .into_inner();
+
+    // `lightwalletd` reports mempool transactions with a height of either 0 or -1.
+    let mined_height = u32::try_from(raw_tx.height)
+        .ok()
+        .filter(|height| *height != 0)
+        .map(BlockHeight::from_u32);
+
+    let chain_height = wallet.chain_height().map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "WalletDb::chain_height failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    // The branch ID is only used to parse pre-v5 transactions, which were always mined.
+    let branch_id = BranchId::for_height(
+        wallet.params(),
+        mined_height
+            .or(chain_height.map(|h| h + 1))
+            .unwrap_or(BlockHeight::from_u32(0)),
+    );
+    let tx = Transaction::read(&raw_tx.data[..], branch_id).map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Deserialization.into(),
+            "Failed to parse transaction",
+            Some(format!("{e}")),
+        )
+    })?;
+    if tx.txid() != txid {
+        return Err(RpcErrorCode::InternalError.into());
+    }
+
+    let before = tx_outputs(wallet, txid)?;
+
+    let params = *wallet.params();
+    decrypt_and_store_transaction(&params, wallet, &tx, mined_height).map_err(|e| {
+        RpcError::owned(
+            LegacyCode::Database.into(),
+            "decrypt_and_store_transaction failed",
+            Some(format!("{e}")),
+        )
+    })?;
+
+    let after = tx_outputs(wallet, txid)?;
+
+    let discovered = after
+        .into_iter()
+        .filter(|output| !before.contains(output))
+        .map(|output| {
+            // Pool codes used by `zcash_client_sqlite`.
+            let pool = match output.output_pool {
+                0 => "transparent",
+                2 => "sapling",
+                3 => "orchard",
+                _ => return Err(RpcErrorCode::InternalError.into()),
+            };
+
+            Ok(Output {
+                pool: pool.into(),
+                outindex: output.output_index,
+                from_account_uuid: output
+                    .from_account_uuid
+                    .as_deref()
+                    .map(encode_uuid)
+                    .transpose()?,
+                to_account_uuid: output
+                    .to_account_uuid
+                    .as_deref()
+                    .map(encode_uuid)
+                    .transpose()?,
+                amount: value_from_zatoshis(
+                    Zatoshis::from_u64(output.value).map_err(|_| RpcErrorCode::InternalError)?,
+                ),
+            })
+        })
+        .collect::<RpcResult<_>>()?;
+
+    Ok(RescanTransaction {
+        txid: txid.to_string(),
+        height: mined_height.map(u32::from),
+        discovered,
+    })
+}
+
+fn tx_outputs(wallet: &WalletConnection, txid: TxId) -> RpcResult<Vec<TxOutput>> {
+    wallet
+        .with_raw(|conn| {
+            conn.prepare(
+                "SELECT output_pool, output_index, from_account_uuid, to_account_uuid, value
+                FROM v_tx_outputs
+                WHERE txid = :txid",
+            )?
+            .query_map(named_params! {":txid": txid.as_ref()}, |row| {
+                Ok(TxOutput {
+                    output_pool: row.get("output_pool")?,
+                    output_index: row.get("output_index")?,
+                    from_account_uuid: row.get("from_account_uuid")?,
+                    to_account_uuid: row.get("to_account_uuid")?,
+                    value: row.get("value")?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query v_tx_outputs",
+                Some(format!("{e}")),
+            )
+        })
+}
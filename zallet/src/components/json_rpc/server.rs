@@ -1,5 +1,8 @@
 //! JSON-RPC server that is compatible with `zcashd`.
 
+use std::io;
+use std::net::SocketAddr;
+
 use jsonrpsee::{
     server::{RpcServiceBuilder, Server},
     tracing::info,
@@ -8,8 +11,8 @@ use tokio::task::JoinHandle;
 
 use crate::{
     components::wallet::Wallet,
-    config::RpcSection,
-    error::{Error, ErrorKind},
+    config::ZalletConfig,
+    error::{with_sources, Error, ErrorKind},
 };
 
 use super::methods::{RpcImpl, RpcServer as _};
@@ -17,24 +20,47 @@ use super::methods::{RpcImpl, RpcServer as _};
 mod error;
 pub(crate) use error::LegacyCode;
 
+mod authorization;
 mod http_request_compatibility;
 mod rpc_call_compatibility;
 
+#[cfg(test)]
+mod tests;
+
 type ServerTask = JoinHandle<Result<(), Error>>;
 
-pub(crate) async fn spawn(config: RpcSection, wallet: Wallet) -> Result<ServerTask, Error> {
+/// Spawns the RPC server, returning its task along with the address it is listening on.
+pub(crate) async fn spawn(
+    config: &ZalletConfig,
+    wallet: Wallet,
+) -> Result<(ServerTask, SocketAddr), Error> {
+    let rpc = &config.rpc;
+
     // Caller should make sure `bind` only contains a single address (for now).
-    assert_eq!(config.bind.len(), 1);
-    let listen_addr = config.bind[0];
+    assert_eq!(rpc.bind.len(), 1);
+    let listen_addr = rpc.bind[0];
 
     // Initialize the RPC methods.
-    let rpc_impl = RpcImpl::new(wallet);
+    let rpc_impl = RpcImpl::new(config, wallet);
+
+    // Set up cookie-based authentication, if enabled.
+    let cookie = rpc
+        .cookie_path
+        .as_deref()
+        .map(authorization::Cookie::create)
+        .transpose()?;
+    let credentials = cookie
+        .iter()
+        .map(|cookie| cookie.credential().to_owned())
+        .collect();
 
+    let authorization_layer = authorization::AuthorizationLayer::new(credentials);
     let http_middleware_layer = http_request_compatibility::HttpRequestMiddlewareLayer::new();
 
     let http_middleware = tower::ServiceBuilder::new()
+        .layer(authorization_layer)
         .layer(http_middleware_layer)
-        .timeout(config.timeout());
+        .timeout(rpc.timeout());
 
     let rpc_middleware = RpcServiceBuilder::new()
         .rpc_logger(1024)
@@ -46,18 +72,32 @@ pub(crate) async fn spawn(config: RpcSection, wallet: Wallet) -> Result<ServerTa
         .set_rpc_middleware(rpc_middleware)
         .build(listen_addr)
         .await
-        .map_err(|e| ErrorKind::Init.context(e))?;
-    let addr = server_instance
-        .local_addr()
-        .map_err(|e| ErrorKind::Init.context(e))?;
+        .map_err(|e| {
+            let hint = if e.kind() == io::ErrorKind::AddrInUse {
+                " (is another Zallet instance already running with this config?)"
+            } else {
+                ""
+            };
+            ErrorKind::Init.context(format!(
+                "json_rpc: failed to listen on {listen_addr} from rpc.bind: {}{hint}",
+                with_sources(&e),
+            ))
+        })?;
+    let addr = server_instance.local_addr().map_err(|e| {
+        ErrorKind::Init.context(format!(
+            "json_rpc: failed to read local address for {listen_addr}: {e}"
+        ))
+    })?;
     info!("Opened RPC endpoint at {}", addr);
 
     let rpc_module = rpc_impl.into_rpc();
 
     let server_task = tokio::spawn(async move {
         server_instance.start(rpc_module).stopped().await;
+        // Remove the cookie file now that it can no longer be used.
+        drop(cookie);
         Ok(())
     });
 
-    Ok(server_task)
+    Ok((server_task, addr))
 }
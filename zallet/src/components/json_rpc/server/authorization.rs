@@ -0,0 +1,208 @@
+//! HTTP basic authentication for the JSON-RPC server.
+
+use std::fs;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use futures::{FutureExt, TryFutureExt};
+use hyper::{header, StatusCode};
+use jsonrpsee::{
+    core::BoxError,
+    server::{HttpBody, HttpRequest, HttpResponse},
+    tracing::{info, warn},
+};
+use rand::{rngs::OsRng, RngCore};
+use tower::Service;
+
+use crate::error::{Error, ErrorKind};
+
+/// The username used for cookie-based authentication, matching `zcashd`.
+const COOKIE_USER: &str = "__cookie__";
+
+/// A cookie file containing a randomly-generated RPC credential.
+///
+/// The file is deleted when this is dropped.
+pub(super) struct Cookie {
+    path: PathBuf,
+    credential: String,
+}
+
+impl Cookie {
+    /// Generates a new credential, and writes it to the cookie file at `path`.
+    ///
+    /// Any existing cookie file is replaced.
+    pub(super) fn create(path: &Path) -> Result<Self, Error> {
+        let mut secret = [0; 32];
+        OsRng.fill_bytes(&mut secret);
+        let credential = format!("{COOKIE_USER}:{}", hex::encode(secret));
+
+        // The file mode is only applied when a file is created, so we remove any existing
+        // cookie file (which might have broader permissions, or be a symlink) and then
+        // require that the file be newly created.
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+        .and_then(|()| options.open(path))
+        .and_then(|mut f| f.write_all(credential.as_bytes()))
+        .map_err(|e| {
+            ErrorKind::Init.context(format!(
+                "Failed to write RPC cookie file {}: {e}",
+                path.display(),
+            ))
+        })?;
+        info!("Wrote RPC cookie file to {}", path.display());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            credential,
+        })
+    }
+
+    /// Returns the credential in the form `user:password`.
+    pub(super) fn credential(&self) -> &str {
+        &self.credential
+    }
+}
+
+impl Drop for Cookie {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!(
+                "Failed to delete RPC cookie file {}: {e}",
+                self.path.display(),
+            );
+        }
+    }
+}
+
+/// HTTP middleware that requires requests to present one of a set of credentials via
+/// HTTP basic authentication.
+///
+/// If the set of credentials is empty, all requests are allowed.
+#[derive(Clone, Debug)]
+pub struct Authorization<S> {
+    service: S,
+    credentials: Arc<Vec<String>>,
+}
+
+impl<S> Authorization<S> {
+    /// Returns `true` if the request headers contain an accepted credential.
+    fn is_authorized(&self, headers: &header::HeaderMap) -> bool {
+        if self.credentials.is_empty() {
+            return true;
+        }
+
+        let provided = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Basic "))
+            .and_then(|value| BASE64_STANDARD.decode(value.trim()).ok());
+
+        provided.map_or(false, |provided| {
+            self.credentials
+                .iter()
+                .any(|credential| constant_time_eq(credential.as_bytes(), &provided))
+        })
+    }
+}
+
+/// Implements [`tower::Layer`] for [`Authorization`].
+#[derive(Clone)]
+pub struct AuthorizationLayer {
+    credentials: Arc<Vec<String>>,
+}
+
+impl AuthorizationLayer {
+    /// Creates a new `AuthorizationLayer` that accepts the given credentials, each in
+    /// the form `user:password`.
+    pub fn new(credentials: Vec<String>) -> Self {
+        Self {
+            credentials: Arc::new(credentials),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for AuthorizationLayer {
+    type Service = Authorization<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        Authorization {
+            service,
+            credentials: self.credentials.clone(),
+        }
+    }
+}
+
+impl<S> Service<HttpRequest<HttpBody>> for Authorization<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse> + Clone + Send + 'static,
+    S::Error: Into<BoxError> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: HttpRequest<HttpBody>) -> Self::Future {
+        if self.is_authorized(request.headers()) {
+            self.service.call(request).map_err(Into::into).boxed()
+        } else {
+            warn!("Rejected RPC request with missing or incorrect credentials");
+            let response = HttpResponse::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header(header::WWW_AUTHENTICATE, "Basic realm=\"jsonrpc\"")
+                .body(HttpBody::empty())
+                .map_err(Into::into);
+            async move { response }.boxed()
+        }
+    }
+}
+
+/// Compares two byte strings in time that depends only on their lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::Cookie;
+
+    #[test]
+    fn cookie_replaces_existing_file_permissions() {
+        let path = std::env::temp_dir().join(format!("zallet-cookie-{}", std::process::id()));
+        fs::write(&path, "stale").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let cookie = Cookie::create(&path).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), cookie.credential());
+
+        drop(cookie);
+        assert!(!path.exists());
+    }
+}
@@ -98,13 +98,9 @@ impl<S> HttpRequestMiddleware<S> {
     /// Maps whatever JSON-RPC version the client is using to JSON-RPC 2.0.
     async fn request_to_json_rpc_2(
         request: HttpRequest<HttpBody>,
-    ) -> (JsonRpcVersion, HttpRequest<HttpBody>) {
+    ) -> Result<(JsonRpcVersion, HttpRequest<HttpBody>), BoxError> {
         let (parts, body) = request.into_parts();
-        let bytes = body
-            .collect()
-            .await
-            .expect("Failed to collect body data")
-            .to_bytes();
+        let bytes = body.collect().await?.to_bytes();
 
         let (version, bytes) =
             if let Ok(request) = serde_json::from_slice::<'_, JsonRpcRequest>(bytes.as_ref()) {
@@ -121,23 +117,19 @@ impl<S> HttpRequestMiddleware<S> {
                 (JsonRpcVersion::Unknown, bytes)
             };
 
-        (
+        Ok((
             version,
             HttpRequest::from_parts(parts, HttpBody::from(bytes.as_ref().to_vec())),
-        )
+        ))
     }
 
     /// Maps JSON-2.0 to whatever JSON-RPC version the client is using.
     async fn response_from_json_rpc_2(
         version: JsonRpcVersion,
         response: HttpResponse<HttpBody>,
-    ) -> HttpResponse<HttpBody> {
+    ) -> Result<HttpResponse<HttpBody>, BoxError> {
         let (parts, body) = response.into_parts();
-        let bytes = body
-            .collect()
-            .await
-            .expect("Failed to collect body data")
-            .to_bytes();
+        let bytes = body.collect().await?.to_bytes();
 
         let bytes =
             if let Ok(response) = serde_json::from_slice::<'_, JsonRpcResponse>(bytes.as_ref()) {
@@ -148,7 +140,10 @@ impl<S> HttpRequestMiddleware<S> {
                 bytes
             };
 
-        HttpResponse::from_parts(parts, HttpBody::from(bytes.as_ref().to_vec()))
+        Ok(HttpResponse::from_parts(
+            parts,
+            HttpBody::from(bytes.as_ref().to_vec()),
+        ))
     }
 }
 
@@ -196,9 +191,9 @@ where
         let mut service = self.service.clone();
 
         async move {
-            let (version, request) = Self::request_to_json_rpc_2(request).await;
+            let (version, request) = Self::request_to_json_rpc_2(request).await?;
             let response = service.call(request).await.map_err(Into::into)?;
-            Ok(Self::response_from_json_rpc_2(version, response).await)
+            Self::response_from_json_rpc_2(version, response).await
         }
         .boxed()
     }
@@ -281,8 +276,13 @@ impl JsonRpcResponse {
             }
             JsonRpcVersion::TwoPointZero => {
                 // `jsonrpsee` should be returning valid JSON-RPC 2.0 responses.
-                assert_eq!(self.jsonrpc.as_deref(), Some("2.0"));
-                assert_eq!(self.result.is_some(), self.error.is_none());
+                debug_assert_eq!(self.jsonrpc.as_deref(), Some("2.0"));
+                debug_assert!(self.result.is_none() || self.error.is_none());
+                // A `null` result deserializes as `None`; restore it so that the response
+                // still has a result.
+                if self.error.is_none() {
+                    self.result = self.result.or(Some(serde_json::Value::Null));
+                }
             }
             JsonRpcVersion::Unknown => (),
         }
@@ -5,7 +5,7 @@
 
 use futures::future::BoxFuture;
 use jsonrpsee::{
-    server::middleware::rpc::{layer::ResponseFuture, RpcService, RpcServiceT},
+    server::middleware::rpc::{layer::ResponseFuture, RpcServiceT},
     tracing::debug,
     types::ErrorObject,
     MethodResponse,
@@ -25,41 +25,37 @@ use crate::components::json_rpc::server::error::LegacyCode;
 /// But these codes are different from `zcashd`, and some RPC clients rely on the exact code.
 /// Specifically, the [`jsonrpsee::types::error::INVALID_PARAMS_CODE`] is different:
 /// <https://docs.rs/jsonrpsee-types/latest/jsonrpsee_types/error/constant.INVALID_PARAMS_CODE.html>
-pub struct FixRpcResponseMiddleware {
-    service: RpcService,
+pub struct FixRpcResponseMiddleware<S> {
+    service: S,
 }
 
-impl FixRpcResponseMiddleware {
+impl<S> FixRpcResponseMiddleware<S> {
     /// Create a new `FixRpcResponseMiddleware` with the given `service`.
-    pub fn new(service: RpcService) -> Self {
+    pub fn new(service: S) -> Self {
         Self { service }
     }
 }
 
-impl<'a> RpcServiceT<'a> for FixRpcResponseMiddleware {
+impl<'a, S> RpcServiceT<'a> for FixRpcResponseMiddleware<S>
+where
+    S: RpcServiceT<'a> + Clone + Send + Sync + 'a,
+{
     type Future = ResponseFuture<BoxFuture<'a, MethodResponse>>;
 
     fn call(&self, request: jsonrpsee::types::Request<'a>) -> Self::Future {
         let service = self.service.clone();
+        // The request ID can be a string, a number, or null, so we take it from the
+        // request rather than parsing it back out of the response.
+        let id = request.id.clone().into_owned();
         ResponseFuture::future(Box::pin(async move {
             let response = service.call(request).await;
-            if response.is_error() {
-                let original_error_code = response
-                    .as_error_code()
-                    .expect("response should have an error code");
+            if let Some(original_error_code) = response.as_error_code() {
                 if original_error_code == jsonrpsee::types::ErrorCode::InvalidParams.code() {
                     let new_error_code = LegacyCode::Misc.into();
                     debug!("Replacing RPC error: {original_error_code} with {new_error_code}");
-                    let json: serde_json::Value =
-                        serde_json::from_str(response.into_parts().0.as_str())
-                            .expect("response string should be valid json");
-                    let id = json["id"]
-                        .as_str()
-                        .expect("response json should have an id")
-                        .to_string();
 
                     return MethodResponse::error(
-                        jsonrpsee::types::Id::Str(id.into()),
+                        id,
                         ErrorObject::borrowed(new_error_code, "Invalid params", None),
                     );
                 }
@@ -0,0 +1,319 @@
+//! Fuzz-style tests for the JSON-RPC compatibility middleware.
+//!
+//! These feed arbitrary bytes, headers, and JSON through the middleware layers and check
+//! that they never panic, and that they always produce a well-formed response.
+
+use futures::{future::BoxFuture, FutureExt};
+use http_body_util::BodyExt;
+use hyper::header::{self, HeaderName, HeaderValue};
+use jsonrpsee::{
+    core::BoxError,
+    server::{middleware::rpc::RpcServiceT, HttpBody, HttpRequest, HttpResponse},
+    types::{ErrorObject, Request, ResponsePayload},
+    MethodResponse,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::{json, Value};
+use tower::Service;
+
+use super::{
+    error::LegacyCode, http_request_compatibility::HttpRequestMiddleware,
+    rpc_call_compatibility::FixRpcResponseMiddleware,
+};
+
+const ITERATIONS: usize = 2000;
+
+fn arbitrary_string(rng: &mut impl Rng) -> String {
+    let len = rng.gen_range(0..8);
+    (0..len).map(|_| rng.gen::<char>()).collect()
+}
+
+fn arbitrary_json(rng: &mut impl Rng, depth: usize) -> Value {
+    match rng.gen_range(0..if depth == 0 { 4 } else { 6 }) {
+        0 => Value::Null,
+        1 => Value::Bool(rng.gen()),
+        2 => match rng.gen_range(0..3) {
+            0 => json!(rng.gen::<i64>()),
+            1 => json!(rng.gen::<u64>()),
+            _ => json!(rng.gen::<f64>()),
+        },
+        3 => Value::String(arbitrary_string(rng)),
+        4 => Value::Array(
+            (0..rng.gen_range(0..4))
+                .map(|_| arbitrary_json(rng, depth - 1))
+                .collect(),
+        ),
+        _ => Value::Object(
+            (0..rng.gen_range(0..4))
+                .map(|_| (arbitrary_string(rng), arbitrary_json(rng, depth - 1)))
+                .collect(),
+        ),
+    }
+}
+
+/// Returns a JSON object that looks like a JSON-RPC request from one of the versions we
+/// support, with some of its fields missing or replaced by arbitrary values.
+fn arbitrary_request(rng: &mut impl Rng) -> Value {
+    let mut request = serde_json::Map::new();
+
+    match rng.gen_range(0..6) {
+        0 => (),
+        1 => {
+            request.insert("jsonrpc".into(), "1.0".into());
+        }
+        2 => {
+            request.insert("jsonrpc".into(), "1.1".into());
+        }
+        3 => {
+            request.insert("jsonrpc".into(), arbitrary_json(rng, 1));
+        }
+        _ => {
+            request.insert("jsonrpc".into(), "2.0".into());
+        }
+    }
+    if rng.gen_bool(0.9) {
+        let method = if rng.gen_bool(0.9) {
+            Value::String(arbitrary_string(rng))
+        } else {
+            arbitrary_json(rng, 1)
+        };
+        request.insert("method".into(), method);
+    }
+    if rng.gen_bool(0.7) {
+        let params = match rng.gen_range(0..3) {
+            0 => Value::Array(
+                (0..rng.gen_range(0..4))
+                    .map(|_| arbitrary_json(rng, 2))
+                    .collect(),
+            ),
+            1 => json!({ "arg": arbitrary_json(rng, 2) }),
+            _ => arbitrary_json(rng, 2),
+        };
+        request.insert("params".into(), params);
+    }
+    if rng.gen_bool(0.8) {
+        let id = match rng.gen_range(0..3) {
+            0 => json!(rng.gen::<u32>()),
+            1 => Value::String(arbitrary_string(rng)),
+            _ => arbitrary_json(rng, 1),
+        };
+        request.insert("id".into(), id);
+    }
+
+    Value::Object(request)
+}
+
+fn arbitrary_body(rng: &mut impl Rng) -> Vec<u8> {
+    match rng.gen_range(0..5) {
+        0 => (0..rng.gen_range(0..64)).map(|_| rng.gen()).collect(),
+        1 => serde_json::to_vec(&arbitrary_json(rng, 3)).expect("valid"),
+        2 => {
+            // A batch of requests.
+            let batch = (0..rng.gen_range(0..4))
+                .map(|_| arbitrary_request(rng))
+                .collect::<Vec<_>>();
+            serde_json::to_vec(&batch).expect("valid")
+        }
+        3 => {
+            // A truncated request.
+            let mut body = serde_json::to_vec(&arbitrary_request(rng)).expect("valid");
+            body.truncate(rng.gen_range(0..=body.len()));
+            body
+        }
+        _ => serde_json::to_vec(&arbitrary_request(rng)).expect("valid"),
+    }
+}
+
+fn arbitrary_header_value(rng: &mut impl Rng) -> Option<HeaderValue> {
+    match rng.gen_range(0..5) {
+        0 => Some(HeaderValue::from_static("application/json")),
+        1 => Some(HeaderValue::from_static("text/plain")),
+        2 => Some(HeaderValue::from_static("text/plain; charset=utf-8")),
+        3 => HeaderValue::from_str(&arbitrary_string(rng)).ok(),
+        _ => {
+            let bytes = (0..rng.gen_range(0..16))
+                .map(|_| rng.gen())
+                .collect::<Vec<u8>>();
+            HeaderValue::from_bytes(&bytes).ok()
+        }
+    }
+}
+
+fn arbitrary_http_request(rng: &mut impl Rng) -> HttpRequest {
+    let mut builder = hyper::Request::builder().method("POST").uri("/");
+
+    for _ in 0..rng.gen_range(0..3) {
+        if let Some(value) = arbitrary_header_value(rng) {
+            builder = builder.header(header::CONTENT_TYPE, value);
+        }
+    }
+    for _ in 0..rng.gen_range(0..3) {
+        let name = HeaderName::from_bytes(arbitrary_string(rng).as_bytes());
+        if let (Ok(name), Some(value)) = (name, arbitrary_header_value(rng)) {
+            builder = builder.header(name, value);
+        }
+    }
+
+    builder
+        .body(HttpBody::from(arbitrary_body(rng)))
+        .expect("valid")
+}
+
+/// A stand-in for the `jsonrpsee` HTTP service, which answers single JSON-RPC 2.0
+/// requests and returns a parse error for anything else.
+#[derive(Clone)]
+struct MockHttpService;
+
+impl Service<HttpRequest> for MockHttpService {
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<HttpResponse, BoxError>>;
+
+    fn poll_ready(
+        &mut self,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        async move {
+            let bytes = request.into_body().collect().await?.to_bytes();
+
+            let response = match serde_json::from_slice::<Value>(&bytes) {
+                Ok(Value::Object(request))
+                    if request.get("jsonrpc") == Some(&json!("2.0"))
+                        && request.get("method").is_some_and(Value::is_string) =>
+                {
+                    let id = request.get("id").cloned().unwrap_or(Value::Null);
+                    let method = request["method"].as_str().expect("checked");
+                    match method.len() % 3 {
+                        0 => json!({ "jsonrpc": "2.0", "result": null, "id": id }),
+                        1 => json!({ "jsonrpc": "2.0", "result": method, "id": id }),
+                        _ => json!({
+                            "jsonrpc": "2.0",
+                            "error": { "code": -32602, "message": "Invalid params" },
+                            "id": id,
+                        }),
+                    }
+                }
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "error": { "code": -32700, "message": "Parse error" },
+                    "id": null,
+                }),
+            };
+
+            Ok(HttpResponse::new(HttpBody::from(
+                serde_json::to_vec(&response).expect("valid"),
+            )))
+        }
+        .boxed()
+    }
+}
+
+#[test]
+fn http_request_compatibility_handles_arbitrary_requests() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut middleware = HttpRequestMiddleware::new(MockHttpService);
+
+    for _ in 0..ITERATIONS {
+        let request = arbitrary_http_request(&mut rng);
+
+        let response = futures::executor::block_on(middleware.call(request))
+            .expect("middleware should not fail on arbitrary requests");
+        let bytes = futures::executor::block_on(response.into_body().collect())
+            .expect("body is in memory")
+            .to_bytes();
+
+        let response = serde_json::from_slice::<Value>(&bytes)
+            .expect("response from the mock service is valid JSON");
+        let response = response.as_object().expect("response is an object");
+        assert!(response.contains_key("id"));
+        assert!(response.contains_key("result") || response.contains_key("error"));
+    }
+}
+
+#[test]
+fn content_type_header_fix_handles_arbitrary_headers() {
+    let mut rng = StdRng::seed_from_u64(1);
+
+    for _ in 0..ITERATIONS {
+        let mut request = arbitrary_http_request(&mut rng);
+        HttpRequestMiddleware::<MockHttpService>::insert_or_replace_content_type_header(
+            request.headers_mut(),
+        );
+        assert!(request.headers().contains_key(header::CONTENT_TYPE));
+    }
+}
+
+/// A stand-in for the `jsonrpsee` method call service, which picks a response based on
+/// the method name.
+#[derive(Clone)]
+struct MockRpcService;
+
+impl<'a> RpcServiceT<'a> for MockRpcService {
+    type Future = futures::future::Ready<MethodResponse>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let id = request.id.clone().into_owned();
+        let response = match request.method.len() % 3 {
+            0 => MethodResponse::response(
+                id,
+                ResponsePayload::success(request.method.to_string()),
+                usize::MAX,
+            ),
+            1 => MethodResponse::error(
+                id,
+                ErrorObject::owned(
+                    jsonrpsee::types::ErrorCode::InvalidParams.code(),
+                    "Invalid params",
+                    None::<()>,
+                ),
+            ),
+            _ => MethodResponse::error(
+                id,
+                ErrorObject::owned(LegacyCode::Database.into(), "Database error", None::<()>),
+            ),
+        };
+        futures::future::ready(response)
+    }
+}
+
+#[test]
+fn rpc_call_compatibility_handles_arbitrary_calls() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let middleware = FixRpcResponseMiddleware::new(MockRpcService);
+
+    let mut calls = 0;
+    for _ in 0..ITERATIONS {
+        let body = if rng.gen_bool(0.5) {
+            serde_json::to_vec(&arbitrary_request(&mut rng)).expect("valid")
+        } else {
+            arbitrary_body(&mut rng)
+        };
+
+        // `jsonrpsee` only passes requests that it could parse to this layer.
+        let Ok(request) = serde_json::from_slice::<Request<'_>>(&body) else {
+            continue;
+        };
+        calls += 1;
+
+        let id = serde_json::to_value(&request.id).expect("valid");
+        let method_len = request.method.len();
+        let response = futures::executor::block_on(middleware.call(request));
+
+        let response_json =
+            serde_json::from_str::<Value>(response.as_result()).expect("response is valid JSON");
+        assert_eq!(response_json["id"], id);
+
+        match method_len % 3 {
+            0 => assert!(response.is_success()),
+            1 => assert_eq!(response.as_error_code(), Some(LegacyCode::Misc.into())),
+            _ => assert_eq!(response.as_error_code(), Some(LegacyCode::Database.into())),
+        }
+    }
+
+    // Make sure that the generator is producing enough valid calls to be useful.
+    assert!(calls > ITERATIONS / 50);
+}
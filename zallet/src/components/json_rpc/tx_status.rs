@@ -0,0 +1,171 @@
+//! The status of wallet transactions, as reported by the RPC methods.
+
+use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned as RpcError};
+use rusqlite::named_params;
+use serde::{Deserialize, Serialize};
+use zcash_protocol::{consensus::BlockHeight, TxId};
+
+use crate::components::{json_rpc::server::LegacyCode, wallet::WalletConnection};
+
+/// The number of blocks before a transaction's expiry height at which it is considered
+/// to be "expiring soon".
+const TX_EXPIRING_SOON_THRESHOLD: u32 = 3;
+
+/// The status of a wallet transaction, as reported by the RPC methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum TxStatus {
+    /// The transaction has been mined.
+    Mined,
+
+    /// The transaction has not been mined, and is not about to expire.
+    Waiting,
+
+    /// The transaction has not been mined, and expires within
+    /// [`TX_EXPIRING_SOON_THRESHOLD`] blocks.
+    ExpiringSoon,
+
+    /// The transaction expired without being mined.
+    Expired,
+
+    /// The transaction has not been mined, and one of its inputs has been spent by a
+    /// mined transaction, so it can never be mined.
+    Conflicted,
+}
+
+/// Another wallet transaction that spends some of the same notes or transparent outputs
+/// as a transaction.
+pub(super) struct WalletConflict {
+    pub(super) txid: TxId,
+    pub(super) mined_height: Option<BlockHeight>,
+}
+
+/// Returns the status of a wallet transaction.
+///
+/// One of `["mined", "waiting", "expiringsoon", "expired", "conflicted"]`.
+pub(super) fn tx_status(
+    wallet: &WalletConnection,
+    txid: TxId,
+    mined_height: Option<BlockHeight>,
+    expiry_height: Option<u32>,
+    expired_unmined: bool,
+    chain_height: Option<BlockHeight>,
+) -> RpcResult<TxStatus> {
+    // Only an unmined transaction can be conflicted, so we don't look for conflicts of
+    // mined transactions.
+    let conflict_mined = mined_height.is_none()
+        && wallet_conflicts(wallet, txid)?
+            .iter()
+            .any(|conflict| conflict.mined_height.is_some());
+
+    Ok(status(
+        mined_height,
+        expiry_height,
+        expired_unmined,
+        chain_height,
+        conflict_mined,
+    ))
+}
+
+fn status(
+    mined_height: Option<BlockHeight>,
+    expiry_height: Option<u32>,
+    expired_unmined: bool,
+    chain_height: Option<BlockHeight>,
+    conflict_mined: bool,
+) -> TxStatus {
+    match (mined_height, expiry_height, chain_height) {
+        (Some(_), _, _) => TxStatus::Mined,
+        // An unmined transaction can never be mined once one of its inputs has been
+        // spent by a transaction in the main chain.
+        _ if conflict_mined => TxStatus::Conflicted,
+        _ if expired_unmined => TxStatus::Expired,
+        (None, Some(expiry_height), Some(chain_height))
+            if expiry_height != 0
+                && expiry_height <= u32::from(chain_height) + TX_EXPIRING_SOON_THRESHOLD =>
+        {
+            TxStatus::ExpiringSoon
+        }
+        _ => TxStatus::Waiting,
+    }
+}
+
+/// Returns the other wallet transactions that spend any of the same notes or transparent
+/// outputs as the given transaction.
+pub(super) fn wallet_conflicts(
+    wallet: &WalletConnection,
+    txid: TxId,
+) -> RpcResult<Vec<WalletConflict>> {
+    wallet
+        .with_raw(|conn| {
+            conn.prepare(
+                "WITH spends AS (
+                    SELECT 'sapling' AS pool, sapling_received_note_id AS spent_id,
+                           transaction_id
+                    FROM sapling_received_note_spends
+                    UNION ALL
+                    SELECT 'orchard', orchard_received_note_id, transaction_id
+                    FROM orchard_received_note_spends
+                    UNION ALL
+                    SELECT 'transparent', transparent_received_output_id, transaction_id
+                    FROM transparent_received_output_spends
+                )
+                SELECT DISTINCT other_tx.txid, other_tx.mined_height
+                FROM transactions this_tx
+                JOIN spends ours ON ours.transaction_id = this_tx.id_tx
+                JOIN spends theirs
+                    ON theirs.pool = ours.pool
+                    AND theirs.spent_id = ours.spent_id
+                    AND theirs.transaction_id != ours.transaction_id
+                JOIN transactions other_tx ON other_tx.id_tx = theirs.transaction_id
+                WHERE this_tx.txid = :txid
+                ORDER BY other_tx.id_tx",
+            )?
+            .query_map(named_params! {":txid": txid.as_ref()}, |row| {
+                Ok(WalletConflict {
+                    txid: TxId::from_bytes(row.get("txid")?),
+                    mined_height: row
+                        .get::<_, Option<u32>>("mined_height")?
+                        .map(BlockHeight::from_u32),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            RpcError::owned(
+                LegacyCode::Database.into(),
+                "Failed to query conflicting transactions",
+                Some(format!("{e}")),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use zcash_protocol::consensus::BlockHeight;
+
+    use super::{status, TxStatus};
+
+    #[test]
+    fn conflicted_only_applies_to_unmined_transactions() {
+        let tip = Some(BlockHeight::from_u32(1000));
+        let mined = Some(BlockHeight::from_u32(990));
+
+        assert_eq!(status(mined, Some(1010), false, tip, true), TxStatus::Mined);
+        assert_eq!(
+            status(None, Some(1010), false, tip, true),
+            TxStatus::Conflicted
+        );
+        // A conflicted transaction is reported as such even after it expires.
+        assert_eq!(
+            status(None, Some(990), true, tip, true),
+            TxStatus::Conflicted
+        );
+        assert_eq!(status(None, Some(990), true, tip, false), TxStatus::Expired);
+        assert_eq!(
+            status(None, Some(1002), false, tip, false),
+            TxStatus::ExpiringSoon
+        );
+        assert_eq!(status(None, Some(0), false, tip, false), TxStatus::Waiting);
+    }
+}
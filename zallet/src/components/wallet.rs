@@ -1,23 +1,50 @@
 use std::fmt;
+use std::ops::{Deref, DerefMut};
 use std::path::Path;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use abscissa_core::{Component, FrameworkError};
 use abscissa_tokio::TokioComponent;
-use tokio::{task::JoinHandle, time};
-use zcash_client_backend::sync;
+use tokio::{sync::Notify, task::JoinHandle, time};
+use tonic::transport::Channel;
+use zcash_client_backend::{
+    proto::service::{compact_tx_streamer_client::CompactTxStreamerClient, Empty},
+    sync,
+};
+use zcash_protocol::consensus::{BlockHeight, BranchId};
 
 use crate::{
-    error::{Error, ErrorKind},
-    network::Network,
+    error::{with_sources, Error, ErrorKind},
+    network::{self, Network},
+    prelude::*,
     remote::Servers,
 };
 
+mod balance_check;
+
 mod cache;
 
 mod connection;
 pub(crate) use connection::WalletConnection;
 
+mod lock;
+use lock::WalletLock;
+
+mod migrations;
+pub(crate) use migrations::{apply_migrations, pending_migrations};
+
+mod notify;
+pub(crate) use notify::NotifyStatus;
+
+mod rescan;
+pub(crate) use rescan::queue_rescan;
+#[cfg(test)]
+pub(crate) use rescan::testing;
+
+/// The maximum delay between attempts to connect to `lightwalletd`.
+const MAX_CONNECT_RETRY_DELAY: Duration = Duration::from_secs(60);
+
 pub(crate) type WalletHandle = deadpool::managed::Object<connection::WalletManager>;
 
 #[derive(Clone, Component)]
@@ -25,7 +52,19 @@ pub(crate) type WalletHandle = deadpool::managed::Object<connection::WalletManag
 pub(crate) struct Wallet {
     params: Network,
     db_data_pool: connection::WalletPool,
+    /// Held for as long as the wallet is open.
+    _lock: Arc<WalletLock>,
     lightwalletd_server: Servers,
+    /// The connection to `lightwalletd`, once the sync task has established it.
+    ///
+    /// This is shared with the RPC server. The underlying channel reconnects by itself
+    /// if the connection drops.
+    chain_client: Arc<OnceLock<CompactTxStreamerClient<Channel>>>,
+    /// Wakes the sync task early, for example when an import has given it new blocks to
+    /// scan.
+    sync_wakeup: Arc<Notify>,
+    /// The outcome of recent runs of the `notify` command.
+    notify_status: Arc<NotifyStatus>,
 }
 
 impl fmt::Debug for Wallet {
@@ -38,16 +77,43 @@ impl fmt::Debug for Wallet {
 }
 
 impl Wallet {
+    /// Opens the wallet database, taking an exclusive lock on it for as long as the
+    /// wallet (or any of its clones) is open.
     pub fn open(
         path: impl AsRef<Path>,
         params: Network,
         lightwalletd_server: Servers,
     ) -> Result<Self, Error> {
+        let lock = WalletLock::acquire(path.as_ref())?;
         let db_data_pool = connection::pool(path, params)?;
         Ok(Self {
             params,
             db_data_pool,
+            _lock: Arc::new(lock),
             lightwalletd_server,
+            chain_client: Arc::new(OnceLock::new()),
+            sync_wakeup: Arc::new(Notify::new()),
+            notify_status: Arc::new(NotifyStatus::default()),
+        })
+    }
+
+    /// Opens a connection to the wallet database, without connecting to the network.
+    ///
+    /// This is for commands that inspect or repair the wallet outside of `zallet start`.
+    /// It takes the same exclusive lock as [`Wallet::open`], so it fails if a running
+    /// Zallet is using the wallet.
+    pub(crate) async fn open_offline(
+        path: impl AsRef<Path>,
+        params: Network,
+    ) -> Result<OfflineWallet, Error> {
+        let lock = WalletLock::acquire(path.as_ref())?;
+        let handle = connection::pool(path, params)?
+            .get()
+            .await
+            .map_err(|e| ErrorKind::Generic.context(e))?;
+        Ok(OfflineWallet {
+            handle,
+            _lock: lock,
         })
     }
 
@@ -63,12 +129,89 @@ impl Wallet {
             .map_err(|e| ErrorKind::Generic.context(e).into())
     }
 
-    pub async fn spawn_sync(&self) -> Result<JoinHandle<Result<(), Error>>, Error> {
-        let mut client = self
-            .lightwalletd_server
+    /// Returns the `lightwalletd` server that the wallet syncs with.
+    pub(crate) fn lightwalletd_server(&self) -> Result<String, Error> {
+        self.lightwalletd_server
+            .pick(self.params)
+            .map(|server| server.to_string())
+    }
+
+    /// Opens a new connection to the configured `lightwalletd` server.
+    pub(crate) async fn connect_lightwalletd(
+        &self,
+    ) -> Result<CompactTxStreamerClient<Channel>, Error> {
+        self.lightwalletd_server
             .pick(self.params)?
             .connect_direct()
-            .await?;
+            .await
+    }
+
+    /// Returns the connection to `lightwalletd`, or `None` if the sync task has not yet
+    /// connected to it.
+    pub(crate) fn chain_client(&self) -> Option<CompactTxStreamerClient<Channel>> {
+        self.chain_client.get().cloned()
+    }
+
+    /// Returns the outcome of recent runs of the `notify` command.
+    pub(crate) fn notify_status(&self) -> &NotifyStatus {
+        &self.notify_status
+    }
+
+    /// Wakes the sync task so that it starts its next pass immediately, instead of
+    /// waiting for the rest of its idle interval.
+    ///
+    /// Call this after changing the wallet in a way that gives it new blocks to scan.
+    pub(crate) fn wake_sync(&self) {
+        self.sync_wakeup.notify_one();
+    }
+
+    /// Opens a new connection to the configured `lightwalletd` server, retrying with
+    /// exponential backoff if it is unreachable.
+    ///
+    /// Gives up after `max_retries` failed retries, or never if `max_retries` is `None`.
+    async fn connect_lightwalletd_with_retries(
+        &self,
+        max_retries: Option<u32>,
+    ) -> Result<CompactTxStreamerClient<Channel>, Error> {
+        let mut retries = 0;
+        let mut delay = Duration::from_secs(1);
+        loop {
+            match self.connect_lightwalletd().await {
+                Ok(client) => return Ok(client),
+                Err(e) if max_retries.map_or(true, |max| retries < max) => {
+                    warn!(
+                        "{}; retrying in {} seconds",
+                        with_sources(e.cause()),
+                        delay.as_secs(),
+                    );
+                    time::sleep(delay).await;
+                    retries += 1;
+                    delay = (delay * 2).min(MAX_CONNECT_RETRY_DELAY);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Spawns a task that keeps the wallet in sync with the chain, scanning `batch_size`
+    /// blocks at a time and waiting `idle_interval` between sync passes.
+    ///
+    /// The task first connects to `lightwalletd`, retrying up to `connect_retries` times
+    /// (or forever if `None`) so that Zallet can start while `lightwalletd` is down. The
+    /// connection is then shared with the RPC server via [`Wallet::chain_client`].
+    ///
+    /// Before each sync pass, the task warns if a network upgrade activates within
+    /// `upgrade_warning_blocks` of the chain tip. If a `notify_command` is given, it is
+    /// run for each wallet transaction that changes.
+    pub async fn spawn_sync(
+        &self,
+        batch_size: u32,
+        idle_interval: Duration,
+        connect_retries: Option<u32>,
+        upgrade_warning_blocks: u32,
+        notify_command: Option<String>,
+    ) -> Result<JoinHandle<Result<(), Error>>, Error> {
+        let wallet = self.clone();
 
         let params = self.params.clone();
 
@@ -76,26 +219,162 @@ impl Wallet {
 
         let mut db_data = self.handle().await?;
 
-        let mut interval = time::interval(Duration::from_secs(30));
+        let mut interval = time::interval(idle_interval);
+
+        let sync_wakeup = self.sync_wakeup.clone();
+
+        // Changes are detected by comparing the wallet's transactions before and after
+        // each sync pass, which also picks up changes made via the RPC server.
+        let mut tx_states = notify_command
+            .is_some()
+            .then(|| notify::TxStates::read(db_data.as_ref()))
+            .transpose()?;
 
         let task = tokio::spawn(async move {
+            let mut client = wallet
+                .connect_lightwalletd_with_retries(connect_retries)
+                .await?;
+            info!("Connected to lightwalletd; starting wallet sync");
+            // The sync task is the only writer, so this cannot already be set.
+            let _ = wallet.chain_client.set(client.clone());
+
             loop {
                 // TODO: Move this inside `sync::run` so that we aren't querying subtree roots
                 // every interval.
-                interval.tick().await;
+                tokio::select! {
+                    _ = interval.tick() => (),
+                    _ = sync_wakeup.notified() => interval.reset(),
+                }
+
+                check_consensus_branch(&mut client, &params, upgrade_warning_blocks).await;
 
                 sync::run(
                     &mut client,
                     &params,
                     &mut db_cache,
                     db_data.as_mut(),
-                    10_000,
+                    batch_size,
                 )
                 .await
                 .map_err(|e| ErrorKind::Generic.context(e))?;
+
+                if let (Some(command), Some(tx_states)) = (&notify_command, &mut tx_states) {
+                    let new_states = notify::TxStates::read(db_data.as_ref())?;
+                    for txid in tx_states.changed(&new_states) {
+                        notify::run(command, txid, wallet.notify_status.clone());
+                    }
+                    *tx_states = new_states;
+                }
             }
         });
 
         Ok(task)
     }
+
+    /// Spawns a task that checks the consistency of the wallet's balances every
+    /// `interval`.
+    ///
+    /// Inconsistencies are logged rather than treated as errors, so the task only exits
+    /// if the wallet database cannot be read.
+    pub(crate) async fn spawn_balance_check(
+        &self,
+        interval: Duration,
+    ) -> Result<JoinHandle<Result<(), Error>>, Error> {
+        let db_data = self.handle().await?;
+
+        let mut interval = time::interval(interval);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
+        let task = tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                balance_check::run(db_data.as_ref())?;
+            }
+        });
+
+        Ok(task)
+    }
+}
+
+/// A connection to a wallet database opened with [`Wallet::open_offline`].
+///
+/// The wallet stays locked until this is dropped.
+pub(crate) struct OfflineWallet {
+    handle: WalletHandle,
+    _lock: WalletLock,
+}
+
+impl Deref for OfflineWallet {
+    type Target = WalletConnection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
+
+impl DerefMut for OfflineWallet {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.handle
+    }
+}
+
+/// Checks that the chain is using the consensus branch that this version of Zallet
+/// expects at the chain tip, and warns if it is not.
+///
+/// A mismatch usually means that the chain has activated a network upgrade that this
+/// version of Zallet does not know about, so transactions it creates would be rejected.
+/// Viewing the wallet is unaffected, so we don't stop syncing.
+///
+/// Also warns if the next upgrade in Zallet's schedule activates within
+/// `upgrade_warning_blocks` of the chain tip.
+async fn check_consensus_branch(
+    client: &mut CompactTxStreamerClient<Channel>,
+    params: &Network,
+    upgrade_warning_blocks: u32,
+) {
+    let info = match client.get_lightd_info(Empty {}).await {
+        Ok(info) => info.into_inner(),
+        Err(e) => {
+            warn!(
+                "Failed to fetch chain info from lightwalletd: {}",
+                e.message()
+            );
+            return;
+        }
+    };
+
+    let Ok(chain_tip) = u32::try_from(info.block_height).map(BlockHeight::from_u32) else {
+        return;
+    };
+    let expected = BranchId::for_height(params, chain_tip);
+
+    match u32::from_str_radix(&info.consensus_branch_id, 16).map(BranchId::try_from) {
+        Ok(Ok(branch_id)) if branch_id == expected => (),
+        Ok(Ok(branch_id)) => warn!(
+            "The chain is using consensus branch {:?} at height {}, but this version of Zallet expects {:?}; check that lightwalletd is on the configured network",
+            branch_id, chain_tip, expected,
+        ),
+        Ok(Err(_)) => warn!(
+            "The chain has activated a network upgrade (consensus branch ID {}) that this version of Zallet predates; please upgrade Zallet",
+            info.consensus_branch_id,
+        ),
+        Err(_) => warn!(
+            "lightwalletd reported an invalid consensus branch ID: {}",
+            info.consensus_branch_id,
+        ),
+    }
+
+    if let Some((upgrade, branch_id, activation_height)) = network::next_upgrade(params, chain_tip)
+    {
+        let blocks_remaining = u32::from(activation_height) - u32::from(chain_tip);
+        if blocks_remaining <= upgrade_warning_blocks {
+            warn!(
+                "Network upgrade {} (consensus branch ID {:08x}) activates at height {} in {} blocks; check that lightwalletd and its full node support it",
+                upgrade,
+                u32::from(branch_id),
+                activation_height,
+                blocks_remaining,
+            );
+        }
+    }
 }
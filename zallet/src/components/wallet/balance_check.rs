@@ -0,0 +1,136 @@
+//! A consistency check between the wallet's notes and its transaction history.
+
+use std::collections::BTreeMap;
+
+use abscissa_core::tracing::{debug, error};
+use rusqlite::named_params;
+use uuid::Uuid;
+use zcash_client_backend::data_api::WalletRead;
+
+use crate::error::{Error, ErrorKind};
+
+use super::WalletConnection;
+
+/// An account's balance, computed in two independent ways.
+#[derive(Default)]
+struct Balances {
+    /// The sum of the account's transactions' effects on its balance.
+    from_transactions: i64,
+
+    /// The total value of the account's unspent outputs, by pool.
+    transparent: i64,
+    sapling: i64,
+    orchard: i64,
+}
+
+impl Balances {
+    fn from_notes(&self) -> i64 {
+        self.transparent + self.sapling + self.orchard
+    }
+}
+
+/// Checks that each account's balance computed from its unspent outputs matches the sum
+/// of its transactions' effects on its balance, and logs an error for each account where
+/// they differ.
+///
+/// Transactions that have expired without being mined are ignored, as are their effects
+/// on the outputs they spent.
+pub(super) fn run(wallet: &WalletConnection) -> Result<(), Error> {
+    let chain_height = wallet
+        .chain_height()
+        .map_err(|e| ErrorKind::Generic.context(e))?
+        .map_or(0, u32::from);
+
+    let rows = wallet
+        .with_raw(|conn| {
+            conn.prepare(
+                "WITH
+                live_tx AS (
+                    SELECT id_tx FROM transactions
+                    WHERE mined_height IS NOT NULL
+                    OR expiry_height IS NULL
+                    OR expiry_height = 0
+                    OR expiry_height > :chain_height
+                ),
+                unspent AS (
+                    SELECT 'transparent' AS pool, o.account_id, o.value_zat AS value
+                    FROM transparent_received_outputs o
+                    WHERE o.transaction_id IN (SELECT id_tx FROM live_tx)
+                    AND NOT EXISTS (
+                        SELECT 1 FROM transparent_received_output_spends s
+                        WHERE s.transparent_received_output_id = o.id
+                        AND s.transaction_id IN (SELECT id_tx FROM live_tx)
+                    )
+                    UNION ALL
+                    SELECT 'sapling', n.account_id, n.value
+                    FROM sapling_received_notes n
+                    WHERE n.tx IN (SELECT id_tx FROM live_tx)
+                    AND NOT EXISTS (
+                        SELECT 1 FROM sapling_received_note_spends s
+                        WHERE s.sapling_received_note_id = n.id
+                        AND s.transaction_id IN (SELECT id_tx FROM live_tx)
+                    )
+                    UNION ALL
+                    SELECT 'orchard', n.account_id, n.value
+                    FROM orchard_received_notes n
+                    WHERE n.tx IN (SELECT id_tx FROM live_tx)
+                    AND NOT EXISTS (
+                        SELECT 1 FROM orchard_received_note_spends s
+                        WHERE s.orchard_received_note_id = n.id
+                        AND s.transaction_id IN (SELECT id_tx FROM live_tx)
+                    )
+                )
+                SELECT accounts.uuid, 'transactions' AS source,
+                    COALESCE(SUM(v.account_balance_delta), 0) AS value
+                FROM accounts
+                LEFT JOIN v_transactions v
+                    ON v.account_uuid = accounts.uuid
+                    AND v.id_tx IN (SELECT id_tx FROM live_tx)
+                GROUP BY accounts.uuid
+                UNION ALL
+                SELECT accounts.uuid, unspent.pool, SUM(unspent.value)
+                FROM unspent
+                JOIN accounts ON accounts.id = unspent.account_id
+                GROUP BY accounts.uuid, unspent.pool",
+            )?
+            .query_map(named_params! {":chain_height": chain_height}, |row| {
+                Ok((
+                    row.get::<_, Vec<u8>>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| ErrorKind::Generic.context(e))?;
+
+    let mut accounts = BTreeMap::<_, Balances>::new();
+    for (account_uuid, source, value) in rows {
+        let balances = accounts.entry(account_uuid).or_default();
+        match source.as_str() {
+            "transactions" => balances.from_transactions = value,
+            "transparent" => balances.transparent = value,
+            "sapling" => balances.sapling = value,
+            "orchard" => balances.orchard = value,
+            _ => unreachable!("source is set by the query"),
+        }
+    }
+
+    for (account_uuid, balances) in accounts {
+        if balances.from_transactions != balances.from_notes() {
+            error!(
+                account_uuid = %Uuid::from_slice(&account_uuid).unwrap_or_default(),
+                chain_height,
+                from_transactions = balances.from_transactions,
+                from_notes = balances.from_notes(),
+                transparent = balances.transparent,
+                sapling = balances.sapling,
+                orchard = balances.orchard,
+                "Wallet balance is inconsistent; please report this as a bug, including this message",
+            );
+        }
+    }
+
+    debug!("Balance consistency check complete");
+    Ok(())
+}
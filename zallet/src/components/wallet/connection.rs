@@ -106,6 +106,31 @@ impl WalletConnection {
             ))
         })
     }
+
+    /// Returns the version of the wallet database schema, which is the number of database
+    /// migrations that have been applied to it.
+    pub(crate) fn schema_version(&self) -> rusqlite::Result<u32> {
+        self.with_raw(super::migrations::schema_version)
+    }
+
+    /// Runs a closure with direct access to the underlying SQLite connection.
+    ///
+    /// This is intended for read-only queries against the views exposed by
+    /// `zcash_client_sqlite` (such as `v_transactions`) that have no equivalent in the
+    /// [`WalletRead`] API.
+    pub(crate) fn with_raw<T>(&self, f: impl FnOnce(&rusqlite::Connection) -> T) -> T {
+        tokio::task::block_in_place(|| f(self.inner.lock().unwrap().as_ref()))
+    }
+
+    /// Runs a closure with direct, mutable access to the underlying SQLite connection.
+    ///
+    /// This is for the few changes that the [`WalletWrite`] API cannot make, such as
+    /// queuing already-scanned blocks to be scanned again. The closure must leave the
+    /// database in a state that `zcash_client_sqlite` expects, so it should make all of
+    /// its changes in a single transaction.
+    pub(crate) fn with_raw_mut<T>(&mut self, f: impl FnOnce(&mut rusqlite::Connection) -> T) -> T {
+        tokio::task::block_in_place(|| f(self.inner.lock().unwrap().as_mut()))
+    }
 }
 
 impl WalletRead for WalletConnection {
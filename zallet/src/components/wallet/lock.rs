@@ -0,0 +1,56 @@
+//! An exclusive lock on the wallet database.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rusqlite::{Connection, ErrorCode};
+
+use crate::error::{Error, ErrorKind};
+
+/// An exclusive lock on a wallet database, which prevents other Zallet processes from
+/// using it while this is held.
+///
+/// The lock is taken on a separate `<wallet_db>.lock` file rather than the wallet
+/// database itself, so that it does not block this process's own connections. It uses
+/// SQLite's file locking, so the operating system releases it if Zallet exits without
+/// dropping it.
+pub(crate) struct WalletLock {
+    /// A connection with an open exclusive transaction, which holds the lock until it is
+    /// closed.
+    _conn: Connection,
+}
+
+impl WalletLock {
+    /// Takes the lock for the given wallet database, failing if another process holds
+    /// it.
+    pub(crate) fn acquire(wallet_db: &Path) -> Result<Self, Error> {
+        let path = lock_path(wallet_db);
+        let lock_error = |e: rusqlite::Error| {
+            ErrorKind::Init.context(format!("lock file {}: {e}", path.display()))
+        };
+
+        let conn = Connection::open(&path).map_err(lock_error)?;
+        // Fail immediately rather than waiting for the other process to exit.
+        conn.busy_timeout(Duration::ZERO).map_err(lock_error)?;
+
+        match conn.execute_batch("BEGIN EXCLUSIVE") {
+            Ok(()) => Ok(Self { _conn: conn }),
+            Err(e) if e.sqlite_error_code() == Some(ErrorCode::DatabaseBusy) => {
+                Err(ErrorKind::Init
+                    .context(format!(
+                        "wallet database {} is in use by another Zallet process; stop it and try again",
+                        wallet_db.display(),
+                    ))
+                    .into())
+            }
+            Err(e) => Err(lock_error(e).into()),
+        }
+    }
+}
+
+fn lock_path(wallet_db: &Path) -> PathBuf {
+    let mut path = OsString::from(wallet_db.as_os_str());
+    path.push(".lock");
+    path.into()
+}
@@ -0,0 +1,161 @@
+//! Applies and checks for wallet database migrations.
+
+use std::fmt;
+use std::path::Path;
+
+use rusqlite::{types::Value, Connection, OpenFlags};
+use zcash_client_sqlite::{wallet::init::init_wallet_db, WalletDb};
+
+use crate::{
+    error::{Error, ErrorKind},
+    network::Network,
+};
+
+/// Applies the database migrations that this version of Zallet needs to the wallet
+/// database at `wallet_db`, creating it if it does not exist, and returns the number of
+/// migrations that were applied.
+pub(crate) fn apply_migrations(wallet_db: &Path, params: Network) -> Result<u32, Error> {
+    let migration_error = |e: &dyn fmt::Display| -> Error {
+        ErrorKind::Init
+            .context(format!(
+                "Cannot apply migrations to {}: {e}",
+                wallet_db.display(),
+            ))
+            .into()
+    };
+
+    let mut db = WalletDb::for_path(wallet_db, params).map_err(|e| migration_error(&e))?;
+    let before = Connection::open(wallet_db)
+        .and_then(|conn| schema_version(&conn))
+        .map_err(|e| migration_error(&e))?;
+    init_wallet_db(&mut db, None).map_err(|e| migration_error(&e))?;
+    let after = Connection::open(wallet_db)
+        .and_then(|conn| schema_version(&conn))
+        .map_err(|e| migration_error(&e))?;
+
+    Ok(after.saturating_sub(before))
+}
+
+/// Returns the number of database migrations that [`apply_migrations`] would apply to
+/// the wallet database at `wallet_db`, without modifying it.
+///
+/// The migrations are applied to an empty in-memory database with the same schema and
+/// record of applied migrations, so that the wallet itself does not need to be copied.
+pub(crate) fn pending_migrations(wallet_db: &Path, params: Network) -> Result<u32, Error> {
+    let check_error = |e: &dyn fmt::Display| -> Error {
+        ErrorKind::Init
+            .context(format!(
+                "Cannot check {} for pending migrations: {e}",
+                wallet_db.display(),
+            ))
+            .into()
+    };
+
+    // The in-memory database lives for as long as `copy` is open, and is shared with the
+    // connection that `WalletDb` opens to it.
+    let copy_uri = format!(
+        "file:zallet-migration-check-{}?mode=memory&cache=shared",
+        std::process::id(),
+    );
+    let copy = Connection::open(&copy_uri).map_err(|e| check_error(&e))?;
+    {
+        let conn = Connection::open_with_flags(wallet_db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| check_error(&e))?;
+        copy_schema(&conn, &copy).map_err(|e| check_error(&e))?;
+    }
+    let before = schema_version(&copy).map_err(|e| check_error(&e))?;
+
+    {
+        let mut db = WalletDb::for_path(&copy_uri, params).map_err(|e| check_error(&e))?;
+        init_wallet_db(&mut db, None).map_err(|e| check_error(&e))?;
+    }
+    let after = schema_version(&copy).map_err(|e| check_error(&e))?;
+
+    Ok(after.saturating_sub(before))
+}
+
+/// Creates the tables, indices, views, and triggers of the database `from` in `to`, and
+/// copies the record of applied migrations.
+fn copy_schema(from: &Connection, to: &Connection) -> rusqlite::Result<()> {
+    // Tables are created first, so that the other objects can refer to them.
+    let statements = from
+        .prepare(
+            "SELECT sql FROM sqlite_master
+            WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+            ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'index' THEN 1 WHEN 'view' THEN 2 ELSE 3 END",
+        )?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for sql in statements {
+        to.execute_batch(&sql)?;
+    }
+
+    if has_schemer(from)? {
+        let mut select = from.prepare("SELECT * FROM _schemer")?;
+        let columns = select.column_count();
+        let mut insert = to.prepare(&format!(
+            "INSERT INTO _schemer VALUES ({})",
+            vec!["?"; columns].join(", "),
+        ))?;
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            let values = (0..columns)
+                .map(|i| row.get::<_, Value>(i))
+                .collect::<Result<Vec<_>, _>>()?;
+            insert.execute(rusqlite::params_from_iter(values))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the version of the wallet database schema, which is the number of database
+/// migrations that have been applied to it.
+pub(super) fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    // `_schemer` records the database migrations that have been applied. A database
+    // that has never been migrated doesn't have it.
+    if has_schemer(conn)? {
+        conn.query_row("SELECT COUNT(*) FROM _schemer", [], |row| row.get(0))
+    } else {
+        Ok(0)
+    }
+}
+
+fn has_schemer(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_schemer')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::{apply_migrations, pending_migrations, schema_version};
+    use crate::components::wallet::testing;
+
+    #[test]
+    fn pending_migrations_are_counted_without_applying_them() {
+        let path =
+            std::env::temp_dir().join(format!("zallet-migrations-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Connection::open(&path).unwrap();
+
+        let pending = pending_migrations(&path, testing::network()).unwrap();
+        assert!(pending > 0);
+        let version = |path| schema_version(&Connection::open(path).unwrap()).unwrap();
+        assert_eq!(version(&path), 0);
+
+        assert_eq!(
+            apply_migrations(&path, testing::network()).unwrap(),
+            pending
+        );
+        assert_eq!(version(&path), pending);
+        assert_eq!(pending_migrations(&path, testing::network()).unwrap(), 0);
+        assert_eq!(apply_migrations(&path, testing::network()).unwrap(), 0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
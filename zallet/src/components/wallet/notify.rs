@@ -0,0 +1,172 @@
+//! Execution of the `notify` command when wallet transactions change.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use abscissa_core::tracing::{error, warn};
+use tokio::{process::Command, time};
+use zcash_protocol::TxId;
+
+use crate::error::{Error, ErrorKind};
+
+use super::WalletConnection;
+
+/// How long the `notify` command may run before it is killed.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The number of consecutive failures of the `notify` command after which they are
+/// logged as errors rather than warnings.
+const NOTIFY_FAILURE_THRESHOLD: u32 = 3;
+
+/// The outcome of recent runs of the `notify` command, which is reported by
+/// `getwalletinfo`.
+#[derive(Debug, Default)]
+pub(crate) struct NotifyStatus(Mutex<NotifyStatusInner>);
+
+#[derive(Debug, Default)]
+struct NotifyStatusInner {
+    consecutive_failures: u32,
+    last_error: Option<String>,
+}
+
+impl NotifyStatus {
+    /// Returns the number of times in a row that the `notify` command has failed.
+    pub(crate) fn consecutive_failures(&self) -> u32 {
+        self.0.lock().expect("not poisoned").consecutive_failures
+    }
+
+    /// Returns the most recent error from the `notify` command, if it has ever failed.
+    pub(crate) fn last_error(&self) -> Option<String> {
+        self.0.lock().expect("not poisoned").last_error.clone()
+    }
+
+    fn succeeded(&self) {
+        self.0.lock().expect("not poisoned").consecutive_failures = 0;
+    }
+
+    /// Records and logs a failure of the `notify` command.
+    fn failed(&self, message: String) {
+        let mut inner = self.0.lock().expect("not poisoned");
+        inner.consecutive_failures += 1;
+        if inner.consecutive_failures >= NOTIFY_FAILURE_THRESHOLD {
+            error!(
+                "{message} ({} failures in a row; notifications are being lost)",
+                inner.consecutive_failures,
+            );
+        } else {
+            warn!("{message}");
+        }
+        inner.last_error = Some(message);
+    }
+}
+
+/// The mined height (if any) of every transaction in the wallet.
+///
+/// Comparing two snapshots detects transactions that were added, mined, unmined, or
+/// removed, regardless of which code path wrote the change to the wallet.
+pub(super) struct TxStates(HashMap<TxId, Option<u32>>);
+
+impl TxStates {
+    /// Reads the current state of every transaction in the wallet.
+    pub(super) fn read(wallet: &WalletConnection) -> Result<Self, Error> {
+        wallet
+            .with_raw(|conn| {
+                conn.prepare("SELECT txid, mined_height FROM transactions")?
+                    .query_map([], |row| {
+                        Ok((
+                            row.get::<_, [u8; 32]>("txid")?,
+                            row.get::<_, Option<u32>>("mined_height")?,
+                        ))
+                    })?
+                    .map(|row| row.map(|(txid, height)| (TxId::from_bytes(txid), height)))
+                    .collect::<Result<_, _>>()
+            })
+            .map(Self)
+            .map_err(|e| ErrorKind::Generic.context(e).into())
+    }
+
+    /// Returns the transactions whose state differs between `self` and `newer`.
+    ///
+    /// Each transaction is returned at most once.
+    pub(super) fn changed(&self, newer: &Self) -> Vec<TxId> {
+        let added_or_updated = newer
+            .0
+            .iter()
+            .filter(|(txid, height)| self.0.get(txid) != Some(height))
+            .map(|(txid, _)| *txid);
+        let removed = self
+            .0
+            .keys()
+            .filter(|txid| !newer.0.contains_key(txid))
+            .copied();
+
+        added_or_updated.chain(removed).collect()
+    }
+}
+
+/// Runs the `notify` command for the given transaction.
+///
+/// `%s` in the command is replaced by the txid. The command runs detached from the
+/// caller, and a failure (including a non-zero exit status) is recorded in `status` and
+/// logged rather than treated as an error. If the command is still running after
+/// [`NOTIFY_TIMEOUT`], it is killed, so that a hung command can't accumulate processes.
+pub(super) fn run(command: &str, txid: TxId, status: Arc<NotifyStatus>) {
+    let command = command.replace("%s", &txid.to_string());
+
+    // Like `zcashd`, run the command with the system shell.
+    #[cfg(not(windows))]
+    let spawned = Command::new("sh").arg("-c").arg(&command).spawn();
+    #[cfg(windows)]
+    let spawned = Command::new("cmd").arg("/C").arg(&command).spawn();
+
+    match spawned {
+        Ok(mut child) => {
+            tokio::spawn(async move {
+                match time::timeout(NOTIFY_TIMEOUT, child.wait()).await {
+                    Ok(Ok(exit_status)) if exit_status.success() => status.succeeded(),
+                    Ok(Ok(exit_status)) => status.failed(format!(
+                        "notify command `{command}` exited with {exit_status}"
+                    )),
+                    Ok(Err(e)) => status.failed(format!(
+                        "Failed to wait for notify command `{command}`: {e}"
+                    )),
+                    Err(_) => {
+                        status.failed(format!(
+                            "notify command `{command}` did not exit within {} seconds; killing it",
+                            NOTIFY_TIMEOUT.as_secs(),
+                        ));
+                        // This also waits for the command to exit, so it doesn't remain
+                        // as a zombie process.
+                        if let Err(e) = child.kill().await {
+                            warn!("Failed to kill notify command `{command}`: {e}");
+                        }
+                    }
+                }
+            });
+        }
+        Err(e) => status.failed(format!("Failed to run notify command `{command}`: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NotifyStatus;
+
+    #[test]
+    fn notify_status_counts_consecutive_failures() {
+        let status = NotifyStatus::default();
+        assert_eq!(status.consecutive_failures(), 0);
+        assert_eq!(status.last_error(), None);
+
+        status.failed("first".into());
+        status.failed("second".into());
+        assert_eq!(status.consecutive_failures(), 2);
+        assert_eq!(status.last_error().as_deref(), Some("second"));
+
+        // A success resets the count, but the last error is kept for diagnosis.
+        status.succeeded();
+        assert_eq!(status.consecutive_failures(), 0);
+        assert_eq!(status.last_error().as_deref(), Some("second"));
+    }
+}
@@ -0,0 +1,237 @@
+//! Queues blocks that the wallet has already scanned to be scanned again.
+
+use std::ops::Range;
+
+use zcash_protocol::consensus::BlockHeight;
+
+/// The `scan_queue` priority that `zcash_client_sqlite` uses for `ScanPriority::Historic`.
+///
+/// The sync task scans every range with a priority above `ScanPriority::Scanned` (10).
+const PRIORITY_HISTORIC: i64 = 20;
+
+/// Queues every block from `start` up to the chain tip to be scanned again, and returns
+/// the range of blocks that was queued.
+///
+/// Returns `None` if the wallet has not learned of any blocks at or above `start`.
+///
+/// [`WalletWrite::truncate_to_height`] can only roll the wallet back to a height for which
+/// it still has a note commitment tree checkpoint, and it only keeps the last hundred or
+/// so. Instead, we do what `zcash_client_sqlite` does when an account is imported with a
+/// birthday in the scanned range: the blocks are marked in the scan queue as not yet
+/// scanned, and the sync task scans them again on its next pass, updating the wallet's
+/// notes and transactions as it goes.
+///
+/// [`WalletWrite::truncate_to_height`]: zcash_client_backend::data_api::WalletWrite::truncate_to_height
+pub(crate) fn queue_rescan(
+    conn: &rusqlite::Transaction<'_>,
+    start: BlockHeight,
+) -> rusqlite::Result<Option<Range<BlockHeight>>> {
+    let start = u32::from(start);
+    let end = match conn.query_row("SELECT MAX(block_range_end) FROM scan_queue", [], |row| {
+        row.get::<_, Option<u32>>(0)
+    })? {
+        Some(end) if end > start => end,
+        _ => return Ok(None),
+    };
+
+    let queue = conn
+        .prepare(
+            "SELECT block_range_start, block_range_end, priority
+            FROM scan_queue
+            ORDER BY block_range_start",
+        )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    conn.execute("DELETE FROM scan_queue", [])?;
+    let mut insert = conn.prepare(
+        "INSERT INTO scan_queue (block_range_start, block_range_end, priority)
+        VALUES (?1, ?2, ?3)",
+    )?;
+    for (range_start, range_end, priority) in requeue(queue, start..end) {
+        insert.execute((range_start, range_end, priority))?;
+    }
+
+    Ok(Some(
+        BlockHeight::from_u32(start)..BlockHeight::from_u32(end),
+    ))
+}
+
+/// Returns the scan queue with every block in `rescan` given at least historic priority.
+///
+/// The parts of existing ranges outside of `rescan` are kept as they are. Blocks in
+/// `rescan` that are not in any existing range are added with historic priority.
+fn requeue(queue: Vec<(u32, u32, i64)>, rescan: Range<u32>) -> Vec<(u32, u32, i64)> {
+    let mut requeued = vec![];
+    // The first block in `rescan` that we have not yet queued.
+    let mut next = rescan.start;
+
+    for (start, end, priority) in queue {
+        if start < rescan.start {
+            requeued.push((start, end.min(rescan.start), priority));
+        }
+
+        let overlap = start.max(rescan.start)..end.min(rescan.end);
+        if !overlap.is_empty() {
+            if next < overlap.start {
+                requeued.push((next, overlap.start, PRIORITY_HISTORIC));
+            }
+            requeued.push((overlap.start, overlap.end, priority.max(PRIORITY_HISTORIC)));
+            next = overlap.end;
+        }
+
+        if end > rescan.end {
+            requeued.push((start.max(rescan.end), end, priority));
+        }
+    }
+    if next < rescan.end {
+        requeued.push((next, rescan.end, PRIORITY_HISTORIC));
+    }
+
+    requeued.sort_unstable();
+    requeued
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::path::PathBuf;
+
+    use secrecy::SecretVec;
+    use zcash_client_backend::data_api::{AccountBirthday, WalletRead, WalletWrite};
+    use zcash_client_sqlite::{wallet::init::init_wallet_db, WalletDb};
+    use zcash_primitives::block::BlockHash;
+    use zcash_protocol::consensus::BlockHeight;
+
+    use crate::network::Network;
+
+    /// The network that test wallets are created for.
+    pub(crate) fn network() -> Network {
+        Network::Consensus(zcash_protocol::consensus::Network::TestNetwork)
+    }
+
+    /// Creates a wallet database with a single account, which has scanned every block
+    /// from its birthday up to `tip`, and returns its path and the wallet birthday.
+    ///
+    /// The database is created in the temporary directory under a name derived from
+    /// `name`, replacing any database left behind by an earlier test run.
+    pub(crate) fn synced_wallet_db(name: &str, tip: u32) -> (PathBuf, BlockHeight) {
+        let path =
+            std::env::temp_dir().join(format!("zallet-{name}-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut db = WalletDb::for_path(&path, network()).unwrap();
+        init_wallet_db(&mut db, None).unwrap();
+        let birthday = AccountBirthday::from_sapling_activation(&network(), BlockHash([0; 32]));
+        db.create_account("", &SecretVec::new(vec![7; 32]), &birthday, None)
+            .unwrap();
+        db.update_chain_tip(BlockHeight::from_u32(tip)).unwrap();
+
+        // Mark every queued block as scanned, as the sync task would once it caught up.
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute(
+                "UPDATE scan_queue SET priority = 10 WHERE priority > 10",
+                [],
+            )
+            .unwrap();
+        assert!(db.suggest_scan_ranges().unwrap().is_empty());
+
+        (path, birthday.height())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zcash_client_backend::data_api::{scanning::ScanPriority, WalletRead};
+    use zcash_client_sqlite::WalletDb;
+    use zcash_protocol::consensus::BlockHeight;
+
+    use super::{queue_rescan, requeue, testing, PRIORITY_HISTORIC};
+
+    #[test]
+    fn requeue_splits_ranges_and_fills_gaps() {
+        let queue = vec![(0, 100, 0), (100, 150, 10), (160, 200, 50), (200, 300, 10)];
+
+        assert_eq!(
+            requeue(queue, 120..250),
+            vec![
+                (0, 100, 0),
+                (100, 120, 10),
+                (120, 150, PRIORITY_HISTORIC),
+                (150, 160, PRIORITY_HISTORIC),
+                (160, 200, 50),
+                (200, 250, PRIORITY_HISTORIC),
+                (250, 300, 10),
+            ],
+        );
+    }
+
+    #[test]
+    fn queue_rescan_requeues_scanned_blocks() {
+        let tip = 2_000_000;
+        let (path, birthday) = testing::synced_wallet_db("queue-rescan", tip);
+        let start = birthday + 1000;
+
+        let mut conn = rusqlite::Connection::open(&path).unwrap();
+        let tx = conn.transaction().unwrap();
+        let queued = queue_rescan(&tx, start).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(queued, Some(start..BlockHeight::from_u32(tip + 1)));
+
+        // The blocks below the requested height stay scanned, and the rest are scanned
+        // again.
+        let db = WalletDb::for_path(&path, testing::network()).unwrap();
+        let ranges = db.suggest_scan_ranges().unwrap();
+        assert!(!ranges.is_empty());
+        assert!(ranges.iter().all(|range| {
+            range.block_range().start >= start && range.priority() >= ScanPriority::Historic
+        }));
+        assert_eq!(
+            ranges.iter().map(|range| range.block_range().start).min(),
+            Some(start),
+        );
+        assert_eq!(
+            ranges.iter().map(|range| range.block_range().end).max(),
+            Some(BlockHeight::from_u32(tip + 1)),
+        );
+
+        // There is nothing to rescan above the chain tip.
+        let tx = conn.transaction().unwrap();
+        assert_eq!(
+            queue_rescan(&tx, BlockHeight::from_u32(tip + 1)).unwrap(),
+            None
+        );
+
+        drop(tx);
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn queue_rescan_from_birthday_requeues_whole_history() {
+        // `rescanblockchain` and `zap-txes` rescan from the wallet birthday by default,
+        // which is far below the oldest note commitment tree checkpoint of a synced wallet.
+        let tip = 2_000_000;
+        let (path, birthday) = testing::synced_wallet_db("queue-rescan-birthday", tip);
+
+        let mut conn = rusqlite::Connection::open(&path).unwrap();
+        let tx = conn.transaction().unwrap();
+        let queued = queue_rescan(&tx, birthday).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(queued, Some(birthday..BlockHeight::from_u32(tip + 1)));
+
+        let db = WalletDb::for_path(&path, testing::network()).unwrap();
+        let ranges = db.suggest_scan_ranges().unwrap();
+        assert_eq!(
+            ranges
+                .iter()
+                .map(|range| u32::from(range.block_range().end)
+                    - u32::from(range.block_range().start))
+                .sum::<u32>(),
+            tip + 1 - u32::from(birthday),
+        );
+
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,13 +1,19 @@
 //! Zallet Config
 
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use abscissa_core::tracing::warn;
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use zcash_primitives::transaction::fees::zip317::{GRACE_ACTIONS, MARGINAL_FEE};
 use zcash_protocol::consensus::NetworkType;
 
-use crate::network::{Network, RegTestNuParam};
+use crate::{
+    error::{Error, ErrorKind},
+    network::{Network, RegTestNuParam},
+};
 
 /// Zallet Configuration
 ///
@@ -47,6 +53,9 @@ pub struct ZalletConfig {
 
     /// Path to the wallet database file.
     ///
+    /// Zallet also creates a `.lock` file next to it (e.g. `wallet.db.lock`), which
+    /// prevents two Zallet processes from using the wallet at the same time.
+    ///
     /// TODO: If we decide to support a data directory, allow this to have a relative path
     /// within it as well as a default name.
     pub wallet_db: Option<PathBuf>,
@@ -54,9 +63,18 @@ pub struct ZalletConfig {
     /// Settings that affect transactions created by Zallet.
     pub builder: BuilderSection,
 
+    /// Settings for enabling or disabling optional behaviour.
+    pub features: FeaturesSection,
+
+    /// Settings for how Zallet fetches and scans the chain.
+    pub indexer: IndexerSection,
+
     /// Configurable limits on wallet operation (to prevent e.g. memory exhaustion).
     pub limits: LimitsSection,
 
+    /// Settings for Zallet's log output.
+    pub logging: LoggingSection,
+
     pub rpc: RpcSection,
 }
 
@@ -71,7 +89,10 @@ impl Default for ZalletConfig {
             require_backup: None,
             wallet_db: None,
             builder: Default::default(),
+            features: Default::default(),
+            indexer: Default::default(),
             limits: Default::default(),
+            logging: Default::default(),
             rpc: Default::default(),
         }
     }
@@ -99,6 +120,354 @@ impl ZalletConfig {
     pub fn require_backup(&self) -> bool {
         self.require_backup.unwrap_or(true)
     }
+
+    /// Returns the path to the wallet database file.
+    pub(crate) fn wallet_db_path(&self) -> Result<&Path, Error> {
+        let path = self
+            .wallet_db
+            .as_ref()
+            .ok_or_else(|| ErrorKind::Init.context("wallet_db must be set (for now)"))?;
+        if path.is_relative() {
+            return Err(ErrorKind::Init
+                .context("wallet_db must be an absolute path (for now)")
+                .into());
+        }
+        Ok(path)
+    }
+
+    /// Checks that the configured values are usable, beyond what deserialization
+    /// enforces.
+    ///
+    /// This is called when the config is loaded, for every command that uses it, so
+    /// that misconfigurations are reported before the command does anything.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        // Minimum is `TX_EXPIRING_SOON_THRESHOLD + 1`.
+        if self.builder.tx_expiry_delta.is_some_and(|delta| delta < 4) {
+            return Err(ErrorKind::Init
+                .context("builder.tx_expiry_delta must be at least 4")
+                .into());
+        }
+
+        if self.builder.fee_marginal_zatoshis == Some(0) {
+            return Err(ErrorKind::Init
+                .context("builder.fee_marginal_zatoshis must be greater than zero")
+                .into());
+        }
+
+        for (i, addr) in self.rpc.bind.iter().enumerate() {
+            for other in &self.rpc.bind[..i] {
+                if addr == other {
+                    return Err(ErrorKind::Init
+                        .context(format!("rpc.bind contains {addr} more than once"))
+                        .into());
+                }
+                // A wildcard address also listens on every specific address, so binding
+                // both will fail at startup.
+                if addr.port() == other.port()
+                    && addr.is_ipv4() == other.is_ipv4()
+                    && (addr.ip().is_unspecified() || other.ip().is_unspecified())
+                {
+                    warn!("rpc.bind contains both {other} and {addr}, which overlap");
+                }
+            }
+        }
+
+        if self
+            .rpc
+            .cookie_path
+            .as_ref()
+            .is_some_and(|path| path.is_relative())
+        {
+            return Err(ErrorKind::Init
+                .context("rpc.cookie_path must be an absolute path (for now)")
+                .into());
+        }
+
+        if let Some(notify) = &self.notify {
+            // The command is run by a shell, so we can only check the program it starts.
+            let program = notify
+                .split_whitespace()
+                .next()
+                .ok_or_else(|| ErrorKind::Init.context("notify must not be empty"))?;
+            if !is_executable(program) {
+                return Err(ErrorKind::Init
+                    .context(format!(
+                        "notify command '{program}' is not an executable file or on the PATH"
+                    ))
+                    .into());
+            }
+            if !notify.contains("%s") {
+                warn!("notify command does not contain %s, so it won't be given the txid");
+            }
+        }
+
+        if self.indexer.batch_size == Some(0) {
+            return Err(ErrorKind::Init
+                .context("indexer.batch_size must be greater than zero")
+                .into());
+        }
+
+        if matches!(self.features.balance_check_interval, Some(interval) if interval < 60) {
+            return Err(ErrorKind::Init
+                .context("features.balance_check_interval must be at least 60 seconds")
+                .into());
+        }
+
+        if self.indexer.recovery_idle_interval == Some(0) {
+            return Err(ErrorKind::Init
+                .context("indexer.recovery_idle_interval must be greater than zero")
+                .into());
+        }
+
+        Ok(())
+    }
+}
+
+impl ZalletConfig {
+    /// Returns a config in which every setting has a value, for documenting them in
+    /// [`ZalletConfig::generate_example`].
+    ///
+    /// Settings with defaults are set to them; the others are set to example values.
+    fn example() -> Self {
+        Self {
+            broadcast: Some(true),
+            export_dir: Some("/path/to/export/dir".into()),
+            network: NetworkType::Main,
+            notify: Some("/path/to/notify-script %s".into()),
+            regtest_nuparams: vec![
+                RegTestNuParam::try_from("c2d6d0b4:1").expect("valid NU5 parameter")
+            ],
+            require_backup: Some(true),
+            wallet_db: Some("/path/to/wallet.db".into()),
+            builder: BuilderSection {
+                spend_zeroconf_change: Some(true),
+                tx_expiry_delta: Some(40),
+                fee_marginal_zatoshis: Some(u64::from(MARGINAL_FEE)),
+                fee_grace_actions: Some(GRACE_ACTIONS),
+            },
+            features: FeaturesSection {
+                balance_check_interval: Some(3600),
+                deprecated: DeprecatedFeaturesSection {
+                    legacy_getwalletinfo_fields: Some(false),
+                },
+            },
+            indexer: IndexerSection {
+                batch_size: Some(10_000),
+                recovery_idle_interval: Some(30),
+                connect_retries: Some(10),
+                upgrade_warning_blocks: Some(16_128),
+            },
+            limits: LimitsSection {
+                orchard_actions: Some(50),
+            },
+            logging: LoggingSection {
+                format: Some(LogFormat::Full),
+            },
+            rpc: RpcSection {
+                bind: vec!["127.0.0.1:28232".parse().expect("valid address")],
+                timeout: Some(30),
+                cookie_path: Some("/path/to/.cookie".into()),
+            },
+        }
+    }
+
+    /// Generates an example config file that documents every setting.
+    ///
+    /// Every setting except `network` is commented out, so that the example behaves the
+    /// same as a config file that only sets `network`.
+    pub(crate) fn generate_example() -> Result<String, Error> {
+        let example =
+            toml::Value::try_from(Self::example()).map_err(|e| ErrorKind::Generic.context(e))?;
+        let mut output = String::from("# Zallet configuration file\n\n");
+        if let Some(example) = example.as_table() {
+            write_example_table(&mut output, None, example);
+        }
+        Ok(output)
+    }
+}
+
+/// Descriptions of each setting (and section) in the example config.
+const EXAMPLE_DOCS: &[(&str, &str)] = &[
+    (
+        "broadcast",
+        "Whether the wallet should broadcast transactions.",
+    ),
+    ("export_dir", "Directory to be used when exporting data."),
+    (
+        "network",
+        "Network type (\"main\", \"test\", or \"regtest\").",
+    ),
+    (
+        "notify",
+        "Execute command when a wallet transaction changes.\n\
+        `%s` in the command is replaced by TxID.",
+    ),
+    (
+        "regtest_nuparams",
+        "The parameters for regtest mode, as \"<consensus branch ID>:<activation height>\".\n\
+        Ignored if `network` is not \"regtest\".",
+    ),
+    (
+        "require_backup",
+        "Whether to require a confirmed backup of the wallet's mnemonic seed before \
+        generating new spending keys and addresses from it.",
+    ),
+    (
+        "wallet_db",
+        "Path to the wallet database file. Zallet also creates a `.lock` file next to it.",
+    ),
+    (
+        "builder",
+        "Settings that affect transactions created by Zallet.",
+    ),
+    (
+        "builder.spend_zeroconf_change",
+        "Whether to spend unconfirmed transparent change when sending transactions.",
+    ),
+    (
+        "builder.tx_expiry_delta",
+        "The number of blocks after which a transaction created by Zallet that has not \
+        been mined will become invalid. Minimum is 4.",
+    ),
+    (
+        "builder.fee_marginal_zatoshis",
+        "The marginal fee (in zatoshis) per logical action, used to compute ZIP 317 fees.",
+    ),
+    (
+        "builder.fee_grace_actions",
+        "The number of logical actions that every transaction pays for, even if it has \
+        fewer.",
+    ),
+    (
+        "features",
+        "Settings for enabling or disabling optional behaviour.",
+    ),
+    (
+        "features.balance_check_interval",
+        "The number of seconds between balance consistency checks. Minimum is 60.\n\
+        The check is disabled if this is not set.",
+    ),
+    (
+        "features.deprecated",
+        "Deprecated behaviour that can be re-enabled for compatibility with zcashd.",
+    ),
+    (
+        "features.deprecated.legacy_getwalletinfo_fields",
+        "Whether getwalletinfo includes the zcashd fields that have no equivalent in \
+        Zallet (paytxfee, feerule, keypoololdest, and keypoolsize).",
+    ),
+    (
+        "indexer",
+        "Settings for how Zallet fetches and scans the chain.",
+    ),
+    (
+        "indexer.batch_size",
+        "The number of blocks to scan in each batch while syncing. Lower this if Zallet \
+        runs out of memory while catching up with the chain.",
+    ),
+    (
+        "indexer.recovery_idle_interval",
+        "The number of seconds to wait between sync passes once the wallet has caught up \
+        with the chain.",
+    ),
+    (
+        "indexer.connect_retries",
+        "The number of times to retry connecting to lightwalletd when Zallet starts.\n\
+        If this is not set, Zallet retries forever.",
+    ),
+    (
+        "indexer.upgrade_warning_blocks",
+        "The number of blocks before a scheduled network upgrade at which Zallet starts \
+        warning about it.",
+    ),
+    (
+        "limits",
+        "Configurable limits on wallet operation (to prevent e.g. memory exhaustion).",
+    ),
+    (
+        "limits.orchard_actions",
+        "The maximum number of Orchard actions permitted in a constructed transaction.",
+    ),
+    ("logging", "Settings for Zallet's log output."),
+    (
+        "logging.format",
+        "The format of log output (\"full\", \"compact\", or \"json\").",
+    ),
+    ("rpc", "Settings for the JSON-RPC server."),
+    (
+        "rpc.bind",
+        "Addresses to listen for JSON-RPC connections. The RPC server is disabled if \
+        this is not set.\n\
+        If you bind Zallet's RPC port to a public IP address, anyone on the internet can \
+        view your transactions and spend your funds.",
+    ),
+    ("rpc.timeout", "Timeout (in seconds) during HTTP requests."),
+    (
+        "rpc.cookie_path",
+        "Path to a cookie file to use for RPC authentication, compatible with zcashd's \
+        -rpccookiefile.",
+    ),
+];
+
+/// Writes the settings in `table` (which is the section `path`, or the top level of the
+/// config if `None`) to `output` as a documented example.
+fn write_example_table(output: &mut String, path: Option<&str>, table: &toml::Table) {
+    let name = |key: &str| match path {
+        Some(path) => format!("{path}.{key}"),
+        None => key.to_string(),
+    };
+    let write_doc = |output: &mut String, name: &str| {
+        let doc = EXAMPLE_DOCS
+            .iter()
+            .find(|(setting, _)| *setting == name)
+            .map_or("", |(_, doc)| doc);
+        for line in doc.lines() {
+            output.push_str(&format!("# {line}\n"));
+        }
+    };
+
+    // TOML requires a section's settings to come before its subsections.
+    for (key, value) in table.iter().filter(|(_, value)| !value.is_table()) {
+        let name = name(key);
+        write_doc(output, &name);
+        // `network` has no default.
+        let comment = if name == "network" { "" } else { "#" };
+        output.push_str(&format!("{comment}{key} = {value}\n\n"));
+    }
+    for (key, value) in table.iter() {
+        if let Some(section) = value.as_table() {
+            let name = name(key);
+            write_doc(output, &name);
+            output.push_str(&format!("[{name}]\n\n"));
+            write_example_table(output, Some(&name), section);
+        }
+    }
+}
+
+/// Returns `true` if `program` is a path to an executable file, or the name of an
+/// executable file in a directory on the `PATH`.
+fn is_executable(program: &str) -> bool {
+    let is_executable_file = |path: &Path| {
+        path.metadata().is_ok_and(|metadata| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+            }
+            #[cfg(not(unix))]
+            {
+                metadata.is_file()
+            }
+        })
+    };
+
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        is_executable_file(Path::new(program))
+    } else {
+        std::env::var_os("PATH").is_some_and(|paths| {
+            std::env::split_paths(&paths).any(|dir| is_executable_file(&dir.join(program)))
+        })
+    }
 }
 
 /// Transaction builder configuration section.
@@ -115,6 +484,18 @@ pub struct BuilderSection {
     ///
     /// - Minimum: `TX_EXPIRING_SOON_THRESHOLD + 1`
     pub tx_expiry_delta: Option<u16>,
+
+    /// The marginal fee (in zatoshis) per logical action, used to compute ZIP 317 fees
+    /// for transactions created by Zallet.
+    ///
+    /// Raising this can help transactions get mined when the mempool is congested.
+    ///
+    /// - Minimum: 1
+    pub fee_marginal_zatoshis: Option<u64>,
+
+    /// The number of logical actions that every transaction created by Zallet pays for,
+    /// even if it has fewer.
+    pub fee_grace_actions: Option<usize>,
 }
 
 impl BuilderSection {
@@ -135,6 +516,150 @@ impl BuilderSection {
     pub fn tx_expiry_delta(&self) -> u16 {
         self.tx_expiry_delta.unwrap_or(40)
     }
+
+    /// The marginal fee (in zatoshis) per logical action, used to compute ZIP 317 fees
+    /// for transactions created by Zallet.
+    ///
+    /// Default is the ZIP 317 marginal fee of 5000 zatoshis.
+    pub fn fee_marginal_zatoshis(&self) -> u64 {
+        self.fee_marginal_zatoshis
+            .unwrap_or_else(|| u64::from(MARGINAL_FEE))
+    }
+
+    /// The number of logical actions that every transaction created by Zallet pays for,
+    /// even if it has fewer.
+    ///
+    /// Default is the ZIP 317 grace actions value of 2.
+    pub fn fee_grace_actions(&self) -> usize {
+        self.fee_grace_actions.unwrap_or(GRACE_ACTIONS)
+    }
+
+    /// Returns the fee (in zatoshis) for a transaction with the given number of logical
+    /// actions, computed with the ZIP 317 formula and the configured fee parameters.
+    pub fn fee_for_logical_actions(&self, logical_actions: usize) -> u64 {
+        self.fee_marginal_zatoshis()
+            .saturating_mul(logical_actions.max(self.fee_grace_actions()) as u64)
+    }
+}
+
+/// Indexer configuration section.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct IndexerSection {
+    /// The number of blocks to scan in each batch while syncing.
+    ///
+    /// Larger batches scan faster, but every block in a batch is held in memory while it
+    /// is scanned, so dense blocks can exhaust the memory of small machines. Lower this
+    /// if Zallet runs out of memory while catching up with the chain.
+    ///
+    /// - Minimum: 1
+    pub batch_size: Option<u32>,
+
+    /// The number of seconds to wait between sync passes once the wallet has caught up
+    /// with the chain.
+    ///
+    /// The wallet also starts a sync pass immediately when a viewing key is imported
+    /// or a transaction is rescanned, regardless of this interval.
+    ///
+    /// - Minimum: 1
+    pub recovery_idle_interval: Option<u64>,
+
+    /// The number of times to retry connecting to `lightwalletd` when Zallet starts.
+    ///
+    /// Zallet waits between attempts, doubling the wait each time up to one minute.
+    /// While it waits, the RPC server is available, but methods that need the chain
+    /// return an "in warmup" error (-28). If this is not set, Zallet retries forever.
+    pub connect_retries: Option<u32>,
+
+    /// The number of blocks before a scheduled network upgrade at which Zallet starts
+    /// logging a warning about it on each sync pass.
+    ///
+    /// This gives operators time to check that `lightwalletd` and its full node are
+    /// ready for the upgrade. Zallet can only see upgrades in its own schedule; if the
+    /// chain activates an upgrade that this version of Zallet does not know about, that
+    /// is logged once `lightwalletd` reports its consensus branch ID.
+    pub upgrade_warning_blocks: Option<u32>,
+}
+
+impl IndexerSection {
+    /// The number of blocks to scan in each batch while syncing.
+    ///
+    /// Default is 10000.
+    pub fn batch_size(&self) -> u32 {
+        self.batch_size.unwrap_or(10_000)
+    }
+
+    /// The time to wait between sync passes once the wallet has caught up with the
+    /// chain.
+    ///
+    /// Default is 30 seconds.
+    pub fn recovery_idle_interval(&self) -> Duration {
+        Duration::from_secs(self.recovery_idle_interval.unwrap_or(30))
+    }
+
+    /// The number of times to retry connecting to `lightwalletd` when Zallet starts, or
+    /// `None` to retry forever.
+    ///
+    /// Default is `None`.
+    pub fn connect_retries(&self) -> Option<u32> {
+        self.connect_retries
+    }
+
+    /// The number of blocks before a scheduled network upgrade at which Zallet starts
+    /// warning about it.
+    ///
+    /// Default is 16128 (about two weeks).
+    pub fn upgrade_warning_blocks(&self) -> u32 {
+        self.upgrade_warning_blocks.unwrap_or(16_128)
+    }
+}
+
+/// Features configuration section.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeaturesSection {
+    /// The number of seconds between checks that each account's balance, computed from
+    /// its unspent notes and UTXOs, matches the sum of its transactions' effects on its
+    /// balance.
+    ///
+    /// A mismatch indicates a bug in Zallet's accounting, and is logged with enough
+    /// detail to report it. The check is disabled if this is not set.
+    ///
+    /// - Minimum: 60
+    pub balance_check_interval: Option<u64>,
+
+    /// Deprecated behaviour that can be re-enabled for compatibility with `zcashd`.
+    pub deprecated: DeprecatedFeaturesSection,
+}
+
+impl FeaturesSection {
+    /// The time between balance consistency checks, or `None` if they are disabled.
+    ///
+    /// Default is `None`.
+    pub fn balance_check_interval(&self) -> Option<Duration> {
+        self.balance_check_interval.map(Duration::from_secs)
+    }
+}
+
+/// Deprecated features configuration section.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeprecatedFeaturesSection {
+    /// Whether `getwalletinfo` includes the `zcashd` fields that have no equivalent in
+    /// Zallet (`paytxfee`, `feerule`, `keypoololdest`, and `keypoolsize`).
+    ///
+    /// Enable this for monitoring tools that expect these fields to be present.
+    pub legacy_getwalletinfo_fields: Option<bool>,
+}
+
+impl DeprecatedFeaturesSection {
+    /// Whether `getwalletinfo` includes the `zcashd` fields that have no equivalent in
+    /// Zallet.
+    ///
+    /// Default is `false`.
+    pub fn legacy_getwalletinfo_fields(&self) -> bool {
+        self.legacy_getwalletinfo_fields.unwrap_or(false)
+    }
 }
 
 /// Limits configuration section.
@@ -154,6 +679,40 @@ impl LimitsSection {
     }
 }
 
+/// Logging configuration section.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoggingSection {
+    /// The format of log output: `"full"`, `"compact"`, or `"json"`.
+    ///
+    /// The `--log-format` flag takes precedence over this setting.
+    pub format: Option<LogFormat>,
+}
+
+impl LoggingSection {
+    /// The format of log output.
+    ///
+    /// Default is `full`.
+    pub fn format(&self) -> LogFormat {
+        self.format.unwrap_or_default()
+    }
+}
+
+/// The format of log output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable output, with each event's fields and span context.
+    #[default]
+    Full,
+
+    /// Human-readable output, on shorter lines.
+    Compact,
+
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
 /// RPC configuration section.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
@@ -176,6 +735,14 @@ pub struct RpcSection {
 
     /// Timeout (in seconds) during HTTP requests.
     pub timeout: Option<u64>,
+
+    /// Path to a cookie file to use for RPC authentication.
+    ///
+    /// If set, Zallet writes a randomly-generated credential to this file on startup,
+    /// and requires every RPC request to present it via HTTP basic authentication with
+    /// the username `__cookie__` (compatible with `zcashd`'s `-rpccookiefile`). The file
+    /// is deleted when the RPC server shuts down.
+    pub cookie_path: Option<PathBuf>,
 }
 
 impl RpcSection {
@@ -186,3 +753,60 @@ impl RpcSection {
         Duration::from_secs(self.timeout.unwrap_or(30))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ZalletConfig, EXAMPLE_DOCS};
+
+    /// Returns the example config with every setting uncommented.
+    fn uncommented_example() -> String {
+        ZalletConfig::generate_example()
+            .unwrap()
+            .lines()
+            .map(|line| match line.strip_prefix('#') {
+                Some(setting) if !setting.starts_with(' ') && !setting.is_empty() => setting,
+                _ => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn example_config_parses() {
+        // Only `network` is set by default.
+        let config: ZalletConfig = toml::from_str(&ZalletConfig::generate_example().unwrap())
+            .expect("example config should parse");
+        assert!(config.wallet_db.is_none());
+
+        // Every setting in the example is one that Zallet parses.
+        let config: ZalletConfig =
+            toml::from_str(&uncommented_example()).expect("uncommented example should parse");
+        assert_eq!(config.wallet_db, ZalletConfig::example().wallet_db);
+        assert_eq!(
+            config.logging.format,
+            ZalletConfig::example().logging.format
+        );
+    }
+
+    #[test]
+    fn example_config_documents_every_setting() {
+        fn check(path: Option<&str>, table: &toml::Table) {
+            for (key, value) in table {
+                let name = match path {
+                    Some(path) => format!("{path}.{key}"),
+                    None => key.clone(),
+                };
+                assert!(
+                    EXAMPLE_DOCS.iter().any(|(setting, _)| *setting == name),
+                    "{name} is not documented in the example config",
+                );
+                if let Some(section) = value.as_table() {
+                    check(Some(&name), section);
+                }
+            }
+        }
+
+        let example = toml::Value::try_from(ZalletConfig::example()).unwrap();
+        check(None, example.as_table().unwrap());
+    }
+}
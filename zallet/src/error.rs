@@ -47,10 +47,40 @@ impl ErrorKind {
     }
 }
 
+/// Formats an error along with its chain of sources, separated by `: `.
+///
+/// Many errors (e.g. from `tonic` or `deadpool`) describe only their immediate failure
+/// in their `Display` impl, and leave the root cause in their sources.
+pub(crate) fn with_sources(e: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        let next = e.to_string();
+        // Avoid repeating sources that already include their own source's message.
+        if !message.ends_with(&next) {
+            message.push_str(": ");
+            message.push_str(&next);
+        }
+        source = e.source();
+    }
+    message
+}
+
 /// Error type
 #[derive(Debug)]
 pub(crate) struct Error(Box<Context<ErrorKind>>);
 
+impl Error {
+    /// Returns the underlying cause of this error, for including it in the context of
+    /// another error.
+    pub(crate) fn cause(&self) -> &(dyn std::error::Error + 'static) {
+        match self.0.source() {
+            Some(source) => source,
+            None => &*self.0,
+        }
+    }
+}
+
 impl Deref for Error {
     type Target = Context<ErrorKind>;
 
@@ -71,6 +71,47 @@ impl consensus::Parameters for Network {
     }
 }
 
+/// The network upgrades that this version of Zallet knows about, with their consensus
+/// branch IDs, in activation order.
+pub(crate) const KNOWN_UPGRADES: &[(consensus::NetworkUpgrade, consensus::BranchId)] = &[
+    (
+        consensus::NetworkUpgrade::Overwinter,
+        consensus::BranchId::Overwinter,
+    ),
+    (
+        consensus::NetworkUpgrade::Sapling,
+        consensus::BranchId::Sapling,
+    ),
+    (
+        consensus::NetworkUpgrade::Blossom,
+        consensus::BranchId::Blossom,
+    ),
+    (
+        consensus::NetworkUpgrade::Heartwood,
+        consensus::BranchId::Heartwood,
+    ),
+    (
+        consensus::NetworkUpgrade::Canopy,
+        consensus::BranchId::Canopy,
+    ),
+    (consensus::NetworkUpgrade::Nu5, consensus::BranchId::Nu5),
+    (consensus::NetworkUpgrade::Nu6, consensus::BranchId::Nu6),
+];
+
+/// Returns the first network upgrade in this version of Zallet's schedule that activates
+/// after `height`, along with its consensus branch ID and activation height.
+pub(crate) fn next_upgrade<P: consensus::Parameters>(
+    params: &P,
+    height: BlockHeight,
+) -> Option<(consensus::NetworkUpgrade, consensus::BranchId, BlockHeight)> {
+    KNOWN_UPGRADES.iter().find_map(|&(nu, branch_id)| {
+        params
+            .activation_height(nu)
+            .filter(|&activation_height| activation_height > height)
+            .map(|activation_height| (nu, branch_id, activation_height))
+    })
+}
+
 /// A parameter for regtest mode.
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 #[serde(try_from = "&str")]
@@ -136,15 +177,9 @@ pub(crate) mod kind {
             where
                 E: serde::de::Error,
             {
-                match v {
-                    "main" => Ok(NetworkType::Main),
-                    "test" => Ok(NetworkType::Test),
-                    "regtest" => Ok(NetworkType::Regtest),
-                    _ => Err(serde::de::Error::invalid_type(
-                        serde::de::Unexpected::Str(v),
-                        &self,
-                    )),
-                }
+                parse(v).map_err(|_| {
+                    serde::de::Error::invalid_type(serde::de::Unexpected::Str(v), &self)
+                })
             }
         }
 
@@ -155,10 +190,62 @@ pub(crate) mod kind {
         network_type: &NetworkType,
         serializer: S,
     ) -> Result<S::Ok, S::Error> {
-        serializer.serialize_str(match network_type {
+        serializer.serialize_str(name(network_type))
+    }
+
+    /// Parses a network type from its config file representation.
+    pub(crate) fn parse(s: &str) -> Result<NetworkType, String> {
+        match s {
+            "main" => Ok(NetworkType::Main),
+            "test" => Ok(NetworkType::Test),
+            "regtest" => Ok(NetworkType::Regtest),
+            _ => Err(format!("'{s}' must be one of 'main', 'test', or 'regtest'")),
+        }
+    }
+
+    /// Returns the config file representation of a network type.
+    pub(crate) fn name(network_type: &NetworkType) -> &'static str {
+        match network_type {
             NetworkType::Main => "main",
             NetworkType::Test => "test",
             NetworkType::Regtest => "regtest",
-        })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zcash_protocol::consensus::{
+        BlockHeight, BranchId, MainNetwork, NetworkUpgrade, Parameters,
+    };
+
+    use super::{next_upgrade, KNOWN_UPGRADES};
+
+    #[test]
+    fn known_upgrades_match_branch_ids() {
+        for &(upgrade, branch_id) in KNOWN_UPGRADES {
+            let height = MainNetwork.activation_height(upgrade).unwrap();
+            assert_eq!(BranchId::for_height(&MainNetwork, height), branch_id);
+        }
+    }
+
+    #[test]
+    fn next_upgrade_follows_height() {
+        let nu5 = MainNetwork.activation_height(NetworkUpgrade::Nu5).unwrap();
+        let nu6 = MainNetwork.activation_height(NetworkUpgrade::Nu6).unwrap();
+
+        assert_eq!(
+            next_upgrade(&MainNetwork, nu5 - 1),
+            Some((NetworkUpgrade::Nu5, BranchId::Nu5, nu5)),
+        );
+        assert_eq!(
+            next_upgrade(&MainNetwork, nu5),
+            Some((NetworkUpgrade::Nu6, BranchId::Nu6, nu6)),
+        );
+        assert_eq!(next_upgrade(&MainNetwork, nu6), None);
+        assert_eq!(
+            next_upgrade(&MainNetwork, BlockHeight::from_u32(0)).map(|(nu, _, _)| nu),
+            Some(NetworkUpgrade::Overwinter),
+        );
     }
 }
@@ -7,7 +7,7 @@ use zcash_client_backend::proto::service::compact_tx_streamer_client::CompactTxS
 use zcash_protocol::consensus::{NetworkType, Parameters};
 
 use crate::{
-    error::{Error, ErrorKind},
+    error::{with_sources, Error, ErrorKind},
     network::Network,
 };
 
@@ -146,25 +146,23 @@ impl<'a> Server<'a> {
     pub(crate) async fn connect_direct(&self) -> Result<CompactTxStreamerClient<Channel>, Error> {
         info!("Connecting to {}", self);
 
-        let channel =
-            Channel::from_shared(self.endpoint()).map_err(|e| ErrorKind::Generic.context(e))?;
+        let connect_error = |e: &(dyn std::error::Error + 'static)| {
+            ErrorKind::Generic.context(format!("lightwalletd {}: {}", self, with_sources(e)))
+        };
+
+        let channel = Channel::from_shared(self.endpoint()).map_err(|e| connect_error(&e))?;
 
         let channel = if self.use_tls() {
             let tls = ClientTlsConfig::new()
                 .domain_name(self.host.to_string())
                 .with_webpki_roots();
-            channel
-                .tls_config(tls)
-                .map_err(|e| ErrorKind::Generic.context(e))?
+            channel.tls_config(tls).map_err(|e| connect_error(&e))?
         } else {
             channel
         };
 
         Ok(CompactTxStreamerClient::new(
-            channel
-                .connect()
-                .await
-                .map_err(|e| ErrorKind::Generic.context(e))?,
+            channel.connect().await.map_err(|e| connect_error(&e))?,
         ))
     }
 }